@@ -1,12 +1,16 @@
 use clap::Parser;
 use config::{Config, File, FileFormat};
 use rust_fractal::renderer::FractalRenderer;
-use rust_fractal::util::RecolourExr;
+use rust_fractal::settings::RenderSettings;
+use rust_fractal::util::{JsonProgress, KfrFormat, RecolourExr, RenderHandle, extended_to_string_short};
+
+use std::sync::Arc;
+use std::time::Instant;
 
 #[derive(Parser)]
 #[command(version, about)]
 struct Opts {
-    #[clap(short, long, help = "Sets the location file to use")]
+    #[clap(short, long, help = "Sets the location file to use (.toml or Kalles Fraktaler .kfr)")]
     input: Option<String>,
 
     #[clap(short = 'o', long, help = "Sets the options file to use")]
@@ -21,6 +25,22 @@ struct Opts {
         help = "Colours the EXR files in the output directory"
     )]
     colour_exr: bool,
+
+    #[clap(long, help = "Prints the progress as JSON lines instead of a table")]
+    json_progress: bool,
+
+    #[clap(
+        short = 'm',
+        long,
+        help = "Writes a location file at the lowest period minibrot in the image instead of rendering"
+    )]
+    minibrot: Option<String>,
+
+    #[clap(
+        long,
+        help = "Writes a location file at the Misiurewicz point with the lowest period in the image instead of rendering"
+    )]
+    misiurewicz: Option<String>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -37,17 +57,64 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     if let Some(l) = opts.input {
-        builder = builder.add_source(File::with_name(&l).required(true));
+        // Kalles Fraktaler locations are mapped onto the same settings as the location files
+        if l.to_ascii_lowercase().ends_with(".kfr") {
+            builder = builder.add_source(File::new(&l, KfrFormat).required(true));
+        } else {
+            builder = builder.add_source(File::with_name(&l).required(true));
+        }
     };
 
     let settings = builder.build()?;
 
     if opts.colour_exr {
-        let colouring = RecolourExr::new(settings);
-        colouring.colour();
+        let colouring_time = Instant::now();
+        let number_images = RecolourExr::new(settings)?.colour()?;
+
+        println!("Recolouring {} images took {} ms.", number_images, colouring_time.elapsed().as_millis());
     } else {
-        let mut renderer = FractalRenderer::new(settings);
-        renderer.render();
+        // Unknown keys are usually misspelt settings, which would otherwise be silently ignored
+        for key in RenderSettings::unknown_keys(&settings) {
+            eprintln!("warning: unknown setting {}", key);
+        }
+
+        let mut renderer = FractalRenderer::try_new(RenderSettings::from_config(settings)?)?;
+
+        if let Some(path) = opts.minibrot {
+            match renderer.find_minibrot(&RenderHandle::new())? {
+                Some(minibrot) => {
+                    minibrot.save(&path)?;
+                    println!("found minibrot with period {} at zoom {}", minibrot.period, extended_to_string_short(minibrot.zoom));
+                },
+                None => eprintln!("no minibrot found in the image")
+            }
+
+            return Ok(());
+        }
+
+        if let Some(path) = opts.misiurewicz {
+            match renderer.find_misiurewicz(&RenderHandle::new())? {
+                Some(location) => {
+                    location.save(&path)?;
+
+                    let misiurewicz = &location.misiurewicz;
+                    println!("found Misiurewicz point with preperiod {} and period {} at zoom {}, with features repeating every {:.3}x zoom and {:.1} degrees", 
+                        misiurewicz.preperiod, 
+                        misiurewicz.period, 
+                        extended_to_string_short(location.zoom), 
+                        misiurewicz.multiplier.norm().to_float(), 
+                        misiurewicz.multiplier.mantissa.arg().to_degrees());
+                },
+                None => eprintln!("no Misiurewicz point found in the image")
+            }
+
+            return Ok(());
+        }
+
+        if opts.json_progress {
+            renderer.observer = Some(Arc::new(JsonProgress::new(std::io::stdout())));
+        }
+        renderer.render()?;
     }
 
     Ok(())
@@ -0,0 +1,44 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    // The location or julia seed could not be parsed as a complex number
+    InvalidLocation(String),
+    // The zoom could not be parsed into mantissa-exponent form
+    InvalidZoom(String),
+    // The palette colours could not be read or interpolated
+    InvalidPalette(String),
+    // A setting has the wrong type or is outside of the allowed range
+    InvalidSettings(String),
+    // A saved reference orbit could not be read, or was written by a different version
+    InvalidReference(String),
+    // An output file could not be written, with the path of the file
+    Write(String, Box<dyn std::error::Error + Send + Sync>),
+    // An input file or directory could not be read, with the path of the file
+    Read(String, Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidLocation(location) => write!(f, "provided location not valid: {}", location),
+            Error::InvalidZoom(zoom) => write!(f, "provided zoom not valid: {}", zoom),
+            Error::InvalidPalette(reason) => write!(f, "provided palette not valid: {}", reason),
+            Error::InvalidSettings(reason) => write!(f, "provided settings not valid: {}", reason),
+            Error::InvalidReference(reason) => write!(f, "saved reference not valid: {}", reason),
+            Error::Write(path, cause) => write!(f, "could not write {}: {}", path, cause),
+            Error::Read(path, cause) => write!(f, "could not read {}: {}", path, cause),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Write(_, cause) | Error::Read(_, cause) => Some(cause.as_ref()),
+            _ => None
+        }
+    }
+}
@@ -1,3 +1,8 @@
 pub mod renderer;
 pub mod util;
-pub mod math;
\ No newline at end of file
+pub mod math;
+pub mod error;
+pub mod settings;
+
+pub use error::{Error, Result};
+pub use settings::RenderSettings;
@@ -0,0 +1,151 @@
+use crate::util::{ComplexExtended, FloatExtended, FractalType};
+use crate::math::reference::Reference;
+use rayon::prelude::*;
+
+// The neglected non-linear terms need to be below the precision of the perturbation
+const BILINEAR_APPROXIMATION_EPSILON: f64 = 1.0 / (1u64 << 53) as f64;
+
+#[derive(Clone, Copy)]
+pub struct BilinearStep {
+    // z -> a * z + b * c, valid while |z| < radius
+    pub a: ComplexExtended,
+    pub b: ComplexExtended,
+    pub radius: FloatExtended,
+    pub length: usize,
+}
+
+impl BilinearStep {
+    // Combines this step with the step directly after it, Julia sets have no delta of c to include
+    fn merge(&self, next: &BilinearStep, maximum_delta: Option<FloatExtended>) -> BilinearStep {
+        let mut a = next.a * self.a;
+        a.reduce();
+
+        let mut b = next.a * self.b + next.b;
+        b.reduce();
+
+        let mut a_norm = self.a.norm();
+        a_norm.reduce();
+
+        // The delta after this step must still be within the radius of the next step
+        let next_radius = match maximum_delta {
+            Some(maximum_delta) => {
+                let mut b_norm = self.b.norm();
+                b_norm.reduce();
+
+                next.radius - b_norm * maximum_delta
+            },
+            None => next.radius
+        };
+
+        let radius = if next_radius.mantissa > 0.0 && a_norm.mantissa > 0.0 {
+            let temp = next_radius / a_norm;
+
+            if temp < self.radius {
+                temp
+            } else {
+                self.radius
+            }
+        } else {
+            FloatExtended::new(0.0, 0)
+        };
+
+        BilinearStep {
+            a,
+            b,
+            radius,
+            length: self.length + next.length
+        }
+    }
+}
+
+pub struct BilinearApproximation {
+    // Level n contains the steps that skip 2^n iterations, starting from reference index 1
+    pub levels: Vec<Vec<BilinearStep>>,
+    pub enabled: bool,
+}
+
+impl BilinearApproximation {
+    pub fn new(enabled: bool) -> Self {
+        BilinearApproximation {
+            levels: Vec::new(),
+            enabled
+        }
+    }
+
+    pub fn generate(&mut self, reference: &Reference, maximum_delta: FloatExtended, fractal_type: FractalType) {
+        self.levels.clear();
+
+        // The approximation needs a holomorphic formula, other fractals iterate every step
+        if !self.enabled || !fractal_type.holomorphic() {
+            return;
+        }
+
+        let fractal_power = fractal_type.power();
+        let maximum_delta = if reference.julia { None } else { Some(maximum_delta) };
+        let reference_end = reference.current_iteration - reference.start_iteration;
+
+        if reference_end < 2 {
+            return;
+        }
+
+        // The z^2 term is negligible compared to the linear term when |z| < 2 * epsilon * |Z| / (p - 1)
+        let radius_scale = BILINEAR_APPROXIMATION_EPSILON * 2.0 / (fractal_power - 1) as f64;
+
+        // Index 0 of the reference is zero so there is no linear term to use
+        let mut steps = (1..reference_end)
+            .into_par_iter()
+            .map(|index| {
+                let reference_z = reference.reference_extended(index);
+
+                let mut a = reference_z.powi(fractal_power as i32 - 1) * fractal_power as f64;
+                a.reduce();
+
+                let mut radius = reference_z.norm() * radius_scale;
+                radius.reduce();
+
+                BilinearStep {
+                    a,
+                    b: ComplexExtended::new2(1.0, 0.0, 0),
+                    radius,
+                    length: 1
+                }
+            })
+            .collect::<Vec<BilinearStep>>();
+
+        while steps.len() > 1 {
+            let next_level = steps
+                .par_chunks(2)
+                .map(|pair| {
+                    if pair.len() == 2 {
+                        pair[0].merge(&pair[1], maximum_delta)
+                    } else {
+                        pair[0]
+                    }
+                })
+                .collect::<Vec<BilinearStep>>();
+
+            self.levels.push(steps);
+            steps = next_level;
+        }
+
+        self.levels.push(steps);
+    }
+
+    // Finds the longest valid step from this reference index
+    #[inline]
+    pub fn lookup(&self, reference_index: usize, delta_norm: FloatExtended, maximum_length: usize) -> Option<&BilinearStep> {
+        let position = reference_index.checked_sub(1)?;
+
+        if position >= self.levels.first()?.len() {
+            return None;
+        }
+
+        // Steps on level n only start at positions that are multiples of 2^n
+        let highest_level = (position.trailing_zeros() as usize).min(self.levels.len() - 1);
+
+        (0..=highest_level)
+            .rev()
+            .map(|level| &self.levels[level][position >> level])
+            .find(|step| step.length <= maximum_length && delta_norm < step.radius)
+    }
+}
@@ -1,9 +1,13 @@
 mod reference;
 mod perturbation;
 mod series_approximation;
+mod bilinear_approximation;
 mod root_finding;
+mod reference_file;
 
 pub use reference::Reference;
 pub use perturbation::Perturbation;
 pub use series_approximation::SeriesApproximation;
-pub use root_finding::{BoxPeriod, BallMethod, get_nucleus, get_nucleus_position};
\ No newline at end of file
+pub use bilinear_approximation::BilinearApproximation;
+pub use reference_file::ReferenceFile;
+pub use root_finding::{BoxPeriod, BallMethod, MisiurewiczPeriod, get_nucleus, get_nucleus_position, get_misiurewicz, get_misiurewicz_position};
\ No newline at end of file
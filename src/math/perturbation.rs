@@ -1,24 +1,137 @@
 
 
-use crate::util::{FloatExp, FloatExtended, PixelData, data_export::DataExport};
+use crate::util::{FloatExp, FloatExtended, PixelData, RenderHandle, data_export::DataExport};
 
 use rayon::prelude::*;
 use crate::math::reference::Reference;
-use crate::util::{ComplexExtended, ComplexFixed, diff_abs};
+use crate::util::{ComplexExtended, ComplexFixed, diff_abs, diff_abs_extended, to_fixed};
 
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::ops::Mul;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use parking_lot::Mutex;
 
-use crate::math::SeriesApproximation;
+use crate::math::{SeriesApproximation, BilinearApproximation};
 
 const ESCAPE_RADIUS: f64 = 1e16;
 
+// Newton steps used to find an attracting cycle, starting from where the orbit is closest to zero
+const INTERIOR_NEWTON_STEPS: usize = 16;
+
+// The cycle has been found once the Newton step is a small fraction of the distance z moves between pixels
+const INTERIOR_NEWTON_TOLERANCE: f64 = 1e-6;
+
+// Satellite components have a period that is a multiple of the atom domain period, up to this multiple is checked
+const INTERIOR_MAXIMUM_MULTIPLE: usize = 8;
+
 pub struct Perturbation {}
 
+// z after one period of a cycle, with its derivatives with respect to z and c
+struct CycleDerivatives {
+    z: ComplexExtended,
+    dz: ComplexExtended,
+    dc: ComplexExtended,
+    dzdz: ComplexExtended,
+    dcdz: ComplexExtended,
+    // z at the phase of the cycle that is reached at the maximum iteration
+    phase_z: ComplexFixed<f64>,
+}
+
 impl Perturbation {
+    // Jacobian of the abs variations at the full value, as [dx'/dx, dx'/dy, dy'/dx, dy'/dy]
+    // 1: burning ship, 2: tricorn, 3: celtic, 4: buffalo, 5: perpendicular burning ship
+    #[inline(always)]
+    fn abs_variation_matrix<const FRACTAL_TYPE: usize, T: Copy + Mul<f64, Output = T>>(
+        re: T, 
+        im: T, 
+        sign_re: f64, 
+        sign_im: f64, 
+        sign_square_re: f64) -> [T; 4] {
+
+        match FRACTAL_TYPE {
+            1 => [re * 2.0, im * -2.0, im * (2.0 * sign_re * sign_im), re * (2.0 * sign_re * sign_im)],
+            2 => [re * 2.0, im * -2.0, im * -2.0, re * -2.0],
+            3 => [re * (2.0 * sign_square_re), im * (-2.0 * sign_square_re), im * 2.0, re * 2.0],
+            4 => [re * (2.0 * sign_square_re), im * (-2.0 * sign_square_re), im * (-2.0 * sign_re * sign_im), re * (-2.0 * sign_re * sign_im)],
+            _ => [re * 2.0, im * -2.0, im * (-2.0 * sign_im), re * (-2.0 * sign_im)]
+        }
+    }
+
+    #[inline(always)]
+    fn perturb_abs_variation<const FRACTAL_TYPE: usize>(
+        delta_current_mantissa: &mut ComplexFixed<f64>, 
+        z: ComplexFixed<f64>, 
+        delta_reference: ComplexFixed<f64>, 
+        scale_factor_1: f64) {
+
+        let delta_re = delta_current_mantissa.re;
+        let delta_im = delta_current_mantissa.im;
+
+        // Perturbation of the real part and half the imaginary part of z^2
+        let square_re = (2.0 * z.re + delta_re * scale_factor_1) * delta_re - (2.0 * z.im + delta_im * scale_factor_1) * delta_im;
+        let square_im = z.re * delta_im + delta_re * (z.im + delta_im * scale_factor_1);
+
+        match FRACTAL_TYPE {
+            1 => {
+                delta_current_mantissa.re = square_re;
+                delta_current_mantissa.im = 2.0 * diff_abs(z.re * z.im / scale_factor_1, square_im);
+            },
+            2 => {
+                delta_current_mantissa.re = square_re;
+                delta_current_mantissa.im = -2.0 * square_im;
+            },
+            3 => {
+                delta_current_mantissa.re = diff_abs((z.re * z.re - z.im * z.im) / scale_factor_1, square_re);
+                delta_current_mantissa.im = 2.0 * square_im;
+            },
+            4 => {
+                delta_current_mantissa.re = diff_abs((z.re * z.re - z.im * z.im) / scale_factor_1, square_re);
+                delta_current_mantissa.im = -2.0 * diff_abs(z.re * z.im / scale_factor_1, square_im);
+            },
+            _ => {
+                // x|y| = (X + x) * (|Y + y| - |Y|) + x|Y|
+                let temp = diff_abs(z.im / scale_factor_1, delta_im);
+
+                delta_current_mantissa.re = square_re;
+                delta_current_mantissa.im = -2.0 * ((z.re + delta_re * scale_factor_1) * temp + delta_re * z.im.abs());
+            }
+        }
+
+        *delta_current_mantissa += delta_reference;
+    }
+
     #[inline(always)]
+    fn perturb_abs_variation_extended<const FRACTAL_TYPE: usize>(
+        delta_current: &mut ComplexExtended, 
+        z: ComplexExtended) {
+
+        let delta_re = delta_current.real();
+        let delta_im = delta_current.imag();
+        let z_re = z.real();
+        let z_im = z.imag();
+
+        let square_re = (2.0 * z_re + delta_re) * delta_re - (2.0 * z_im + delta_im) * delta_im;
+        let square_im = z_re * delta_im + delta_re * (z_im + delta_im);
+
+        let (new_re, new_im) = match FRACTAL_TYPE {
+            1 => (square_re, 2.0 * diff_abs_extended(z_re * z_im, square_im)),
+            2 => (square_re, -2.0 * square_im),
+            3 => (diff_abs_extended(z_re * z_re - z_im * z_im, square_re), 2.0 * square_im),
+            4 => (diff_abs_extended(z_re * z_re - z_im * z_im, square_re), -2.0 * diff_abs_extended(z_re * z_im, square_im)),
+            _ => {
+                let temp = diff_abs_extended(z_im, delta_im);
+                let z_im_abs = FloatExtended::new(z_im.mantissa.abs(), z_im.exponent);
+
+                (square_re, -2.0 * ((z_re + delta_re) * temp + delta_re * z_im_abs))
+            }
+        };
+
+        *delta_current = ComplexExtended::from_parts(new_re, new_im);
+    }
+
+    #[inline(always)]
+    #[allow(clippy::too_many_arguments)]
     fn perturb_function<const DATA_TYPE: usize, const FRACTAL_TYPE: usize>(
         delta_current_mantissa: &mut ComplexFixed<f64>, 
         jacobian: &mut [ComplexExtended; 2],
@@ -26,32 +139,32 @@ impl Perturbation {
         delta_reference: ComplexFixed<f64>, 
         scale_factor_1: f64,
         scale_factor_2: f64,
-        pascal: &Vec<f64>,
+        pascal: &[f64],
         fractal_power: usize) {
 
         match DATA_TYPE {
             1 | 3 => {
                 match FRACTAL_TYPE {
-                    1 => {
-                        let jacobian_a_copy = jacobian[0].mantissa.clone();
-                        let jacobian_b_copy = jacobian[1].mantissa.clone();
+                    1..=5 => {
+                        let jacobian_a_copy = jacobian[0].mantissa;
+                        let jacobian_b_copy = jacobian[1].mantissa;
 
                         let loc = z + scale_factor_1 * *delta_current_mantissa;
 
-                        let sign = 2.0 * (loc.re * loc.im).signum();
+                        let [p, q, r, t] = Perturbation::abs_variation_matrix::<FRACTAL_TYPE, f64>(
+                            loc.re, 
+                            loc.im, 
+                            loc.re.signum(), 
+                            loc.im.signum(), 
+                            (loc.re * loc.re - loc.im * loc.im).signum());
 
                         // TODO needs to be scaled
-                        jacobian[0].mantissa.re = 2.0 * (loc.re * jacobian_a_copy.re - loc.im * jacobian_b_copy.re) + scale_factor_2;
-                        jacobian[0].mantissa.im = 2.0 * (loc.re * jacobian_a_copy.im - loc.im * jacobian_b_copy.im);
-                        jacobian[1].mantissa.re = sign * (loc.re * jacobian_b_copy.re + loc.im * jacobian_a_copy.re);
-                        jacobian[1].mantissa.im = sign * (loc.re * jacobian_b_copy.im + loc.im * jacobian_a_copy.im) + scale_factor_2;
-
-                        let temp_re = delta_current_mantissa.re;
+                        jacobian[0].mantissa = p * jacobian_a_copy + q * jacobian_b_copy;
+                        jacobian[0].mantissa.re += scale_factor_2;
+                        jacobian[1].mantissa = r * jacobian_a_copy + t * jacobian_b_copy;
+                        jacobian[1].mantissa.im += scale_factor_2;
 
-                        delta_current_mantissa.re = (2.0 * z.re + temp_re * scale_factor_1) * temp_re - (2.0 * z.im + delta_current_mantissa.im * scale_factor_1) * delta_current_mantissa.im;
-                        delta_current_mantissa.im = 2.0 * diff_abs(z.re * z.im / scale_factor_1, z.re * delta_current_mantissa.im + temp_re * (z.im + delta_current_mantissa.im * scale_factor_1));
-
-                        *delta_current_mantissa += delta_reference;
+                        Perturbation::perturb_abs_variation::<FRACTAL_TYPE>(delta_current_mantissa, z, delta_reference, scale_factor_1);
                     }
                     _ => {
                         match fractal_power {
@@ -88,10 +201,10 @@ impl Perturbation {
                                 let mut sum = pascal[1] * z + temp;
                                 let mut z_p = z;
 
-                                for i in 2..fractal_power { 
+                                for &coefficient in &pascal[2..fractal_power] { 
                                     sum *= temp; 
                                     z_p *= z;
-                                    sum += pascal[i] * z_p;
+                                    sum += coefficient * z_p;
                                 }
 
                                 *delta_current_mantissa *= sum;
@@ -103,13 +216,8 @@ impl Perturbation {
             },
             _ => {
                 match FRACTAL_TYPE {
-                    1 => {
-                        let temp_re = delta_current_mantissa.re;
-
-                        delta_current_mantissa.re = (2.0 * z.re + temp_re * scale_factor_1) * temp_re - (2.0 * z.im + delta_current_mantissa.im * scale_factor_1) * delta_current_mantissa.im;
-                        delta_current_mantissa.im = 2.0 * diff_abs(z.re * z.im / scale_factor_1, z.re * delta_current_mantissa.im + temp_re * (z.im + delta_current_mantissa.im * scale_factor_1));
-
-                        *delta_current_mantissa += delta_reference;
+                    1..=5 => {
+                        Perturbation::perturb_abs_variation::<FRACTAL_TYPE>(delta_current_mantissa, z, delta_reference, scale_factor_1);
                     }
                     _ => {
                         match fractal_power {
@@ -133,10 +241,10 @@ impl Perturbation {
                                 let mut sum = pascal[1] * z + temp;
                                 let mut z_p = z;
 
-                                for i in 2..fractal_power { 
+                                for &coefficient in &pascal[2..fractal_power] { 
                                     sum *= temp; 
                                     z_p *= z;
-                                    sum += pascal[i] * z_p;
+                                    sum += coefficient * z_p;
                                 }
 
                                 *delta_current_mantissa *= sum;
@@ -151,45 +259,61 @@ impl Perturbation {
     }
 
     #[inline(always)]
-    fn perturb_function_extended<const DATA_TYPE: usize, const FRACTAL_TYPE: usize>(
+    pub(crate) fn perturb_function_extended<const DATA_TYPE: usize, const FRACTAL_TYPE: usize>(
         delta_current: &mut ComplexExtended, 
         jacobian: &mut [ComplexExtended; 2],
         z: ComplexExtended, 
         delta_reference: ComplexExtended, 
-        pascal: &Vec<f64>,
-        fractal_power: usize) {
+        pascal: &[f64],
+        fractal_power: usize,
+        julia: bool) {
 
         match DATA_TYPE {
             1 | 3 => {
                 match FRACTAL_TYPE {
-                    1 => {
-                        unimplemented!()
+                    1..=5 => {
+                        let jacobian_a_copy = jacobian[0];
+                        let jacobian_b_copy = jacobian[1];
+
+                        let mut loc = z + *delta_current;
+                        loc.reduce();
+
+                        let loc_re = loc.real();
+                        let loc_im = loc.imag();
+
+                        let [p, q, r, t] = Perturbation::abs_variation_matrix::<FRACTAL_TYPE, FloatExtended>(
+                            loc_re, 
+                            loc_im, 
+                            loc.mantissa.re.signum(), 
+                            loc.mantissa.im.signum(), 
+                            (loc_re * loc_re - loc_im * loc_im).mantissa.signum());
+
+                        jacobian[0] = jacobian_a_copy * p + jacobian_b_copy * q;
+                        jacobian[1] = jacobian_a_copy * r + jacobian_b_copy * t;
+
+                        Perturbation::perturb_abs_variation_extended::<FRACTAL_TYPE>(delta_current, z);
                     }
                     _ => {
                         match fractal_power {
                             2 => {
                                 jacobian[0] *= (z + *delta_current) * 2.0;
-                                jacobian[0] += ComplexExtended::new2(1.0, 0.0, 0);
 
                                 *delta_current *= z * 2.0 + *delta_current;
-                                *delta_current += delta_reference;
                             },
                             // This should be a generic implementation for mandelbrot powers > 3
                             _ => {
                                 jacobian[0] *= (z + *delta_current).powi(fractal_power as i32 - 1) * fractal_power as f64;
-                                jacobian[0] += ComplexExtended::new2(1.0, 0.0, 0);
 
                                 let mut sum = z * pascal[1] + *delta_current;
                                 let mut z_p = z;
 
-                                for i in 2..fractal_power { 
+                                for &coefficient in &pascal[2..fractal_power] { 
                                     sum *= *delta_current; 
                                     z_p *= z;
-                                    sum += z_p * pascal[i];
+                                    sum += z_p * coefficient;
                                 }
 
                                 *delta_current *= sum;
-                                *delta_current += delta_reference;
                             }
                         }
                     }
@@ -197,50 +321,280 @@ impl Perturbation {
             },
             _ => {
                 match FRACTAL_TYPE {
-                    1 => {
-                        unimplemented!()
+                    1..=5 => {
+                        Perturbation::perturb_abs_variation_extended::<FRACTAL_TYPE>(delta_current, z);
                     }
                     _ => {
                         match fractal_power {
                             2 => {
                                 *delta_current *= z * 2.0 + *delta_current;
-                                *delta_current += delta_reference;
                             },
                             _ => {
                                 let mut sum = z * pascal[1] + *delta_current;
                                 let mut z_p = z;
 
-                                for i in 2..fractal_power { 
+                                for &coefficient in &pascal[2..fractal_power] { 
                                     sum *= *delta_current; 
                                     z_p *= z;
-                                    sum += z_p * pascal[i];
+                                    sum += z_p * coefficient;
                                 }
 
                                 *delta_current *= sum;
-                                *delta_current += delta_reference;
                             }
                         }
                     }
                 }
             }
         }
+
+        // Julia sets only perturb the initial z, so there is no delta of c to add
+        if !julia {
+            *delta_current += delta_reference;
+
+            if DATA_TYPE == 1 || DATA_TYPE == 3 {
+                jacobian[0] += ComplexExtended::new2(1.0, 0.0, 0);
+
+                if FRACTAL_TYPE != 0 {
+                    jacobian[1] += ComplexExtended::new2(0.0, 1.0, 0);
+                }
+            }
+        }
+    }
+
+    // The delta of c scaled to the current delta, Julia sets only perturb the initial z so nothing is added
+    #[inline(always)]
+    fn scaled_delta_reference(pixel: &PixelData, julia: bool) -> ComplexFixed<f64> {
+        if julia {
+            ComplexFixed::new(0.0, 0.0)
+        } else {
+            1.0f64.ldexp(pixel.delta_reference.exponent - pixel.delta_current.exponent) * pixel.delta_reference.mantissa
+        }
+    }
+
+    // The derivative of c scaled to the current jacobian
+    #[inline(always)]
+    fn scaled_derivative_reference(pixel: &PixelData, julia: bool) -> f64 {
+        if julia {
+            0.0
+        } else {
+            1.0f64.ldexp(-pixel.jacobian_current[0].exponent)
+        }
+    }
+
+    // Iterates one period of a cycle of the mandelbrot from z, relative to the central reference so that it can be rebased,
+    // or returns None if z escapes
+    fn cycle_derivatives<const FRACTAL_POWER: usize>(
+        reference: &Reference, 
+        delta_reference: ComplexExtended, 
+        start: ComplexExtended, 
+        period: usize, 
+        phase: usize, 
+        pascal: &[f64]) -> Option<CycleDerivatives> {
+
+        let reference_end = reference.current_iteration - reference.start_iteration;
+        let power = FRACTAL_POWER as f64;
+        let one = ComplexExtended::new2(1.0, 0.0, 0);
+        let zero = ComplexExtended::new2(0.0, 0.0, 0);
+
+        // The central reference starts from zero, so the delta is the value of z
+        let mut delta_current = start;
+        let mut reference_index = 0;
+        let mut jacobian = [zero, zero];
+
+        let mut cycle = CycleDerivatives {
+            z: start,
+            dz: one,
+            dc: zero,
+            dzdz: zero,
+            dcdz: zero,
+            phase_z: start.to_float(),
+        };
+
+        for k in 0..period {
+            let mut z = reference.reference_extended(reference_index) + delta_current;
+            z.reduce();
+
+            let mut z_norm = z.norm_square();
+            z_norm.reduce();
+
+            if z_norm.to_float() > ESCAPE_RADIUS {
+                return None;
+            }
+
+            if k == phase {
+                cycle.phase_z = z.to_float();
+            }
+
+            let first_derivative = z.powi(FRACTAL_POWER as i32 - 1) * power;
+            let second_derivative = z.powi(FRACTAL_POWER as i32 - 2) * (power * (power - 1.0));
+
+            cycle.dcdz = second_derivative * cycle.dc * cycle.dz + first_derivative * cycle.dcdz;
+            cycle.dzdz = second_derivative * cycle.dz * cycle.dz + first_derivative * cycle.dzdz;
+            cycle.dz = first_derivative * cycle.dz;
+            cycle.dc = first_derivative * cycle.dc + one;
+
+            cycle.dcdz.reduce();
+            cycle.dzdz.reduce();
+            cycle.dz.reduce();
+            cycle.dc.reduce();
+
+            let mut delta_norm = delta_current.norm_square();
+            delta_norm.reduce();
+
+            if z_norm < delta_norm || reference_index == reference_end {
+                delta_current = z;
+                reference_index = 0;
+            }
+
+            Perturbation::perturb_function_extended::<0, 0>(
+                &mut delta_current,
+                &mut jacobian,
+                reference.reference_extended(reference_index),
+                delta_reference,
+                pascal,
+                FRACTAL_POWER,
+                false
+            );
+
+            delta_current.reduce();
+            reference_index += 1;
+        }
+
+        cycle.z = reference.reference_extended(reference_index) + delta_current;
+        cycle.z.reduce();
+
+        Some(cycle)
     }
 
+    // Finds the attracting cycle of the given period with Newton's method, returning z at the phase of the maximum iteration
+    // and the distance estimate to the boundary of the component, or None if the cycle is not attracting
+    pub(crate) fn attracting_cycle<const FRACTAL_POWER: usize>(
+        reference: &Reference, 
+        delta_reference: ComplexExtended, 
+        guess: ComplexExtended, 
+        period: usize, 
+        phase: usize, 
+        delta_pixel: FloatExtended, 
+        pascal: &[f64]) -> Option<(ComplexFixed<f64>, FloatExtended)> {
+
+        let one = ComplexExtended::new2(1.0, 0.0, 0);
+        let mut z = guess;
+        let mut converged = false;
+
+        for _ in 0..INTERIOR_NEWTON_STEPS {
+            let cycle = Perturbation::cycle_derivatives::<FRACTAL_POWER>(reference, delta_reference, z, period, phase, pascal)?;
+
+            let mut step = (cycle.z - z) / (cycle.dz - one);
+            step.reduce();
+
+            if !step.mantissa.re.is_finite() || !step.mantissa.im.is_finite() {
+                return None;
+            }
+
+            z -= step;
+            z.reduce();
+
+            let mut step_norm = step.norm_square();
+            let mut tolerance = (cycle.dc * delta_pixel).norm_square() * INTERIOR_NEWTON_TOLERANCE;
+            step_norm.reduce();
+            tolerance.reduce();
+
+            if step_norm <= tolerance {
+                converged = true;
+                break;
+            }
+        }
+
+        if !converged {
+            return None;
+        }
+
+        let cycle = Perturbation::cycle_derivatives::<FRACTAL_POWER>(reference, delta_reference, z, period, phase, pascal)?;
+
+        let multiplier = cycle.dz.norm_square().to_float();
+
+        if multiplier >= 1.0 {
+            return None;
+        }
+
+        // Interior distance estimate from the derivatives of the cycle
+        let mut denominator = cycle.dcdz + cycle.dzdz * cycle.dc / (one - cycle.dz);
+        denominator.reduce();
+
+        let mut distance = FloatExtended::new(1.0 - multiplier, 0) / denominator.norm();
+        distance.reduce();
+
+        Some((cycle.phase_z, distance))
+    }
+
+    // Checks for an attracting cycle through z at the given iteration, moving on to the next multiple of the atom domain period
+    // if there is none
+    #[inline(never)]
+    fn check_interior<const FRACTAL_POWER: usize>(
+        pixel: &mut PixelData, 
+        reference: &Reference, 
+        z: ComplexExtended, 
+        iteration: usize, 
+        delta_pixel: FloatExtended, 
+        pascal: &[f64]) -> bool {
+
+        let period = if pixel.interior_period > 0 {
+            pixel.interior_period
+        } else {
+            pixel.atom_domain_period
+        };
+
+        let phase = (reference.maximum_iteration - iteration) % period;
+
+        match Perturbation::attracting_cycle::<FRACTAL_POWER>(reference, pixel.delta_reference, z, period, phase, delta_pixel, pascal) {
+            Some((interior_z, interior_distance)) => {
+                pixel.interior_z = interior_z;
+                pixel.interior_distance = interior_distance;
+                pixel.interior_period = period;
+                true
+            },
+            None => {
+                pixel.interior_period = if period < INTERIOR_MAXIMUM_MULTIPLE * pixel.atom_domain_period {
+                    period + pixel.atom_domain_period
+                } else {
+                    usize::MAX
+                };
+
+                false
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn iterate<const DATA_TYPE: usize, const FRACTAL_TYPE: usize, const FRACTAL_POWER: usize>(
         pixel_data: &mut [PixelData], 
         reference: &Reference, 
         pixels_complete: &Arc<AtomicUsize>, 
-        stop_flag: &Arc<AtomicBool>, 
+        handle: &RenderHandle, 
         data_export: Arc<Mutex<DataExport>>, 
         delta_pixel: FloatExtended, 
         scale: usize, 
         chunk_size: usize, 
         series_approximation: &SeriesApproximation, 
-        _initial: bool,
-        pascal: &Vec<f64>) {
+        bilinear_approximation: &BilinearApproximation, 
+        initial: bool,
+        interior_detection: bool,
+        pascal: &[f64]) {
 
         let iterations_before_check = 400 / FRACTAL_POWER;
 
+        // The attracting cycles are only found for the mandelbrot
+        let interior_detection = interior_detection && FRACTAL_TYPE == 0;
+
+        // The atom domain is tracked at every iteration, both for colouring and for the period of the attracting cycle
+        let atom_domain = DATA_TYPE == 4 || interior_detection;
+
+        // The averages need a term from every iteration
+        let average = DATA_TYPE == 5 || DATA_TYPE == 6;
+        let every_iteration = atom_domain || average;
+
+        let reference_c = to_fixed(&reference.c);
+
         pixel_data.par_chunks_mut(chunk_size)
         .for_each(|pixel_data| {
             // Record the number of new pixels that have been completed
@@ -249,49 +603,120 @@ impl Perturbation {
 
             // Iterate through each pixel in the packet of pixels
             for pixel in pixel_data.iter_mut() {
-                // Check if the stop flag has been hit and STOP
-                if stop_flag.load(Ordering::SeqCst) {
+                // Check if the render has been cancelled and STOP, the remaining pixels are left for resuming
+                if handle.should_stop() {
                     break;
                 };
 
-                // Evaluate series approximation
-                pixel.delta_current = series_approximation.evaluate(pixel.delta_reference, pixel.iteration);
+                pixel.iterated = true;
+
+                // Evaluate series approximation, glitched pixels keep their current deltas
+                if initial {
+                    pixel.delta_current = series_approximation.evaluate(pixel.delta_reference, pixel.iteration);
 
-                if DATA_TYPE == 1 || DATA_TYPE == 3 {
-                    pixel.jacobian_current[0] = series_approximation.evaluate_derivative(pixel.delta_reference, pixel.iteration);
-                    pixel.jacobian_current[1].scale_to_exponent(pixel.jacobian_current[0].exponent);
+                    if DATA_TYPE == 1 || DATA_TYPE == 3 {
+                        pixel.jacobian_current = series_approximation.evaluate_jacobian(pixel.delta_reference, pixel.iteration);
+                        pixel.jacobian_current[1].scale_to_exponent(pixel.jacobian_current[0].exponent);
+                    }
                 }
 
                 pixel_index += 1;
 
-                // Get index into reference array
-                let mut reference_index = pixel.iteration;
+                // Get index into reference array, which is stored from the start iteration of the reference
+                let mut reference_index = pixel.reference_iteration - reference.start_iteration;
+                let reference_end = reference.current_iteration - reference.start_iteration;
+
+                // Only the central reference stores the orbit from the origin, which is needed for rebasing
+                let rebasing_allowed = reference.start_iteration == 0;
+
+                // Rebasing goes back to the critical point for the parameter plane, and the location for Julia sets
+                let julia = reference.julia;
+                let rebase_index = if julia { 1 } else { 0 };
+
+                // The triangle inequality average is bounded using |c|
+                let c_norm = if julia {
+                    reference_c.norm()
+                } else {
+                    (reference_c + pixel.delta_reference.to_float()).norm()
+                };
 
                 // Scaled factors and reference values for the scaled double implementation
                 let mut scale_factor_delta = 1.0f64.ldexp(pixel.delta_current.exponent);
-                let mut scale_factor_derivative = 1.0f64.ldexp(-pixel.jacobian_current[0].exponent);
-                let mut scaled_delta_reference = 1.0f64.ldexp(pixel.delta_reference.exponent - pixel.delta_current.exponent) * pixel.delta_reference.mantissa;
+                let mut scale_factor_derivative = Perturbation::scaled_derivative_reference(pixel, julia);
+                let mut scaled_delta_reference = Perturbation::scaled_delta_reference(pixel, julia);
 
                 // Get the number of iterations to the first extended iteration
-                let (mut extended_index, &next_extended_iteration) = reference.extended_iterations
+                let (mut extended_index, mut next_extended_iteration) = reference.extended_iterations
                     .iter()
                     .enumerate()
-                    .find(|&(_, &value)| value >= pixel.iteration)
-                    .unwrap_or((0, &0xFFFFFFFF));
+                    .find(|&(_, &value)| value >= pixel.reference_iteration)
+                    .map_or((0, 0xFFFFFFFF), |(index, &value)| (index, value - reference.start_iteration));
 
-                let mut next_extended_iteration = next_extended_iteration;
-
-                let first_extended_iteration = *reference.extended_iterations.get(0).unwrap_or(&0xFFFFFFFF);
+                let (first_extended_index, first_extended_iteration) = reference.extended_iterations
+                    .iter()
+                    .enumerate()
+                    .find(|&(_, &value)| value > reference.start_iteration + rebase_index)
+                    .map_or((0, 0xFFFFFFFF), |(index, &value)| (index, value - reference.start_iteration));
 
                 // CORE ITERATION LOOP
                 'outer: loop {
+                    // The bilinear approximation is built from the central reference, so it can skip many iterations at once
+                    // The atom domain and the averages need z at every iteration, so nothing is skipped
+                    if !every_iteration && rebasing_allowed && bilinear_approximation.enabled {
+                        let mut skipped = false;
+
+                        loop {
+                            let mut delta_norm = pixel.delta_current.norm();
+                            delta_norm.reduce();
+
+                            // Steps cannot go past extended iterations or the end of the reference
+                            let maximum_length = (next_extended_iteration.min(reference_end) - reference_index)
+                                .min(reference.maximum_iteration - pixel.iteration);
+
+                            match bilinear_approximation.lookup(reference_index, delta_norm, maximum_length) {
+                                Some(step) => {
+                                    pixel.delta_current = if julia {
+                                        step.a * pixel.delta_current
+                                    } else {
+                                        step.a * pixel.delta_current + step.b * pixel.delta_reference
+                                    };
+                                    pixel.delta_current.reduce();
+
+                                    if DATA_TYPE == 1 || DATA_TYPE == 3 {
+                                        pixel.jacobian_current[0] = if julia {
+                                            step.a * pixel.jacobian_current[0]
+                                        } else {
+                                            step.a * pixel.jacobian_current[0] + step.b
+                                        };
+                                        pixel.jacobian_current[0].reduce();
+                                    }
+
+                                    pixel.iteration += step.length;
+                                    reference_index += step.length;
+                                    skipped = true;
+                                },
+                                None => break
+                            }
+                        }
+
+                        if skipped {
+                            if DATA_TYPE == 1 || DATA_TYPE == 3 {
+                                pixel.jacobian_current[1].scale_to_exponent(pixel.jacobian_current[0].exponent);
+                                scale_factor_derivative = Perturbation::scaled_derivative_reference(pixel, julia);
+                            }
+
+                            scale_factor_delta = 1.0f64.ldexp(pixel.delta_current.exponent);
+                            scaled_delta_reference = Perturbation::scaled_delta_reference(pixel, julia);
+                        }
+                    }
+
                     let iterations_remaining = reference.maximum_iteration - pixel.iteration;
                     let next_iteration_batch = iterations_remaining
                         .min(iterations_before_check)
                         .min(next_extended_iteration - reference_index);
 
-                    // If we should be doing escape checks
-                    if pixel.delta_current.exponent > -500 {
+                    // If we should be doing escape checks, which are always needed if the batch reaches the end of the reference
+                    if every_iteration || pixel.delta_current.exponent > -500 || reference_index + next_iteration_batch > reference_end {
                         // for loop to avoid bounds checks
 
 
@@ -306,7 +731,7 @@ impl Perturbation {
                             // Check for escape
                             if z_norm > ESCAPE_RADIUS {
                                 pixel.iteration += i;
-                                pixel.reference_iteration = reference_index;
+                                pixel.reference_iteration = reference.start_iteration + reference_index;
                                 pixel.z_norm = z_norm;
                                 pixel.delta_current.mantissa = pixel.delta_current.to_float();
                                 pixel.delta_current.exponent = 0;
@@ -315,6 +740,17 @@ impl Perturbation {
                                 break 'outer;
                             }
 
+                            // Pauldelbrot's glitch criterion - these pixels are fixed later with a new reference
+                            // The pixels it catches would be rebased below, so it is only needed when rebasing is not allowed
+                            if !rebasing_allowed && z_norm < reference.glitch_tolerance * reference_z.norm_sqr() {
+                                pixel.iteration += i;
+                                pixel.reference_iteration = reference.start_iteration + reference_index;
+                                pixel.z_norm = z_norm;
+                                pixel.glitched = true;
+
+                                break 'outer;
+                            }
+
                             // Add iterations to stripe storage
                             if DATA_TYPE == 2 || DATA_TYPE == 3 {
                                 pixel.stripe_iteration += 1;
@@ -323,15 +759,81 @@ impl Perturbation {
                                 pixel.stripe_storage[pixel.stripe_iteration] = z;
                             }
 
-                            // Check - could be optimised
-                            if z_norm < (scale_factor_delta * pixel.delta_current.mantissa).norm_sqr() || reference_index == reference.current_iteration {
-                                pixel.delta_current.mantissa = z / scale_factor_delta;
+                            if DATA_TYPE == 5 {
+                                pixel.average.add_triangle_inequality(z, c_norm, FRACTAL_POWER as i32);
+                            } else if DATA_TYPE == 6 {
+                                pixel.average.add_curvature(z);
+                            }
+
+                            if atom_domain && pixel.iteration + i > 0 {
+                                if z_norm < pixel.atom_domain_norm {
+                                    pixel.atom_domain_norm = z_norm;
+                                    pixel.atom_domain_period = pixel.iteration + i;
+                                    pixel.interior_period = 0;
+                                }
+
+                                if interior_detection {
+                                    let period = if pixel.interior_period > 0 {
+                                        pixel.interior_period
+                                    } else {
+                                        pixel.atom_domain_period
+                                    };
+
+                                    // A derivative that is small over the last period suggests an attracting cycle
+                                    if (pixel.iteration + i).is_multiple_of(period) {
+                                        if rebasing_allowed && pixel.derivative_z.norm_square().to_float() < 1.0 {
+                                            let mut z_extended = ComplexExtended::new(z, 0);
+                                            z_extended.reduce();
+
+                                            if Perturbation::check_interior::<FRACTAL_POWER>(pixel, reference, z_extended, pixel.iteration + i, delta_pixel, pascal) {
+                                                pixel.iteration = reference.maximum_iteration;
+                                                pixel.reference_iteration = reference.start_iteration + reference_index;
+
+                                                new_pixels_complete += 1;
+                                                break 'outer;
+                                            }
+                                        }
+
+                                        pixel.derivative_z = ComplexExtended::new2(1.0, 0.0, 0);
+                                    }
 
-                                reference_index = 0;
-                                reference_z = reference.reference_data[0];
+                                    pixel.derivative_z.mantissa *= FRACTAL_POWER as f64 * z.powi(FRACTAL_POWER as i32 - 1);
 
-                                extended_index = 0;
-                                next_extended_iteration = first_extended_iteration;
+                                    // Only reduced when needed, as this is done at every iteration
+                                    let derivative_norm = pixel.derivative_z.mantissa.norm_sqr();
+
+                                    if !(1e-200..=1e200).contains(&derivative_norm) {
+                                        pixel.derivative_z.reduce();
+                                    }
+                                }
+                            }
+
+                            // Check - could be optimised
+                            if rebasing_allowed {
+                                let (rebased_z, rebased_norm) = if julia {
+                                    let temp = z - reference.reference_data[rebase_index];
+                                    (temp, temp.norm_sqr())
+                                } else {
+                                    (z, z_norm)
+                                };
+
+                                if rebased_norm < (scale_factor_delta * pixel.delta_current.mantissa).norm_sqr() || reference_index == reference_end {
+                                    pixel.delta_current.mantissa = rebased_z / scale_factor_delta;
+
+                                    reference_index = rebase_index;
+                                    reference_z = reference.reference_data[rebase_index];
+
+                                    extended_index = first_extended_index;
+                                    next_extended_iteration = first_extended_iteration;
+                                }
+                            } else if reference_index == reference_end {
+                                // The reference has escaped, so another reference is needed to continue
+                                pixel.iteration += i;
+                                pixel.reference_iteration = reference.start_iteration + reference_index;
+                                pixel.z_norm = z_norm;
+                                pixel.glitched = true;
+
+                                break 'outer;
                             }
 
                             Perturbation::perturb_function::<DATA_TYPE, FRACTAL_TYPE>(
@@ -371,7 +873,18 @@ impl Perturbation {
                     if pixel.iteration >= reference.maximum_iteration {
                         // println!("hit3 {} {}", pixel.iteration, reference.maximum_iteration);
                         pixel.iteration = reference.maximum_iteration;
-                        pixel.reference_iteration = reference_index;
+                        pixel.reference_iteration = reference.start_iteration + reference_index;
+
+                        let mut z = reference.reference_extended(reference_index) + pixel.delta_current;
+                        z.reduce();
+
+                        pixel.interior_z = z.to_float();
+
+                        // The orbit has reached the cycle by now, so the period that is due to be checked next is tried
+                        if interior_detection && rebasing_allowed && pixel.atom_domain_period > 0 && pixel.interior_period != usize::MAX {
+                            Perturbation::check_interior::<FRACTAL_POWER>(pixel, reference, z, pixel.iteration, delta_pixel, pascal);
+                        }
+
                         new_pixels_complete += 1;
                         break;
                     }
@@ -385,6 +898,7 @@ impl Perturbation {
                         // Check for escape
                         if z_norm > ESCAPE_RADIUS {
                             pixel.iteration += 1;
+                            pixel.reference_iteration = reference.start_iteration + reference_index;
                             pixel.z_norm = z_norm;
                             pixel.delta_current.mantissa = pixel.delta_current.to_float();
                             pixel.delta_current.exponent = 0;
@@ -393,6 +907,15 @@ impl Perturbation {
                             break 'outer;
                         }
 
+                        // Pauldelbrot's glitch criterion, the same as in the batched iterations
+                        if !rebasing_allowed && z_norm < reference.glitch_tolerance * reference_z.norm_sqr() {
+                            pixel.reference_iteration = reference.start_iteration + reference_index;
+                            pixel.z_norm = z_norm;
+                            pixel.glitched = true;
+
+                            break 'outer;
+                        }
+
                         // Add iterations to stripe storage
                         if DATA_TYPE == 2 || DATA_TYPE == 3 {
                             pixel.stripe_iteration += 1;
@@ -401,39 +924,67 @@ impl Perturbation {
                             pixel.stripe_storage[pixel.stripe_iteration] = z;
                         }
 
-                        // Check - could be optimised
-                        if z_norm < (scale_factor_delta * pixel.delta_current.mantissa).norm_sqr() || reference_index == reference.current_iteration {
-                            pixel.delta_current.mantissa = z / scale_factor_delta;
+                        if DATA_TYPE == 5 {
+                            pixel.average.add_triangle_inequality(z, c_norm, FRACTAL_POWER as i32);
+                        } else if DATA_TYPE == 6 {
+                            pixel.average.add_curvature(z);
+                        }
 
-                            reference_index = 0;
+                        if atom_domain {
+                            if z_norm < pixel.atom_domain_norm {
+                                pixel.atom_domain_norm = z_norm;
+                                pixel.atom_domain_period = pixel.iteration;
+                                pixel.interior_period = 0;
+                            }
+
+                            if interior_detection {
+                                pixel.derivative_z.mantissa *= FRACTAL_POWER as f64 * z.powi(FRACTAL_POWER as i32 - 1);
+                                pixel.derivative_z.reduce();
+                            }
+                        }
 
-                            extended_index = 0;
+                        let rebased_z = if rebasing_allowed {
+                            z - reference.reference_data[rebase_index]
+                        } else {
+                            z
+                        };
+
+                        // Check - could be optimised
+                        if rebasing_allowed && (rebased_z.norm_sqr() < (scale_factor_delta * pixel.delta_current.mantissa).norm_sqr() || reference_index == reference_end) {
+                            pixel.delta_current.mantissa = rebased_z / scale_factor_delta;
+
+                            extended_index = first_extended_index;
                             next_extended_iteration = first_extended_iteration;
 
                             Perturbation::perturb_function::<DATA_TYPE, FRACTAL_TYPE>(
                                 &mut pixel.delta_current.mantissa,
                                 &mut pixel.jacobian_current,
-                                reference.reference_data[0],
+                                reference.reference_data[rebase_index],
                                 scaled_delta_reference,
                                 scale_factor_delta,
                                 scale_factor_derivative,
                                 pascal,
                                 FRACTAL_POWER
                             );
+
+                            reference_index = rebase_index + 1;
                         } else {
                             Perturbation::perturb_function_extended::<DATA_TYPE, FRACTAL_TYPE>(
                                 &mut pixel.delta_current,
                                 &mut pixel.jacobian_current,
-                                reference.reference_data_extended[reference_index],
+                                reference.reference_extended(reference_index),
                                 pixel.delta_reference,
                                 pascal,
-                                FRACTAL_POWER
+                                FRACTAL_POWER,
+                                julia
                             );
 
                             extended_index += 1;
                             reference_index += 1;
 
-                            next_extended_iteration = *reference.extended_iterations.get(extended_index).unwrap_or(&0xFFFFFFFF);
+                            next_extended_iteration = reference.extended_iterations
+                                .get(extended_index)
+                                .map_or(0xFFFFFFFF, |&value| value - reference.start_iteration);
                         }
 
                         pixel.iteration += 1;
@@ -444,11 +995,11 @@ impl Perturbation {
                     if DATA_TYPE == 1 || DATA_TYPE == 3 {
                         pixel.jacobian_current[0].reduce();
                         pixel.jacobian_current[1].scale_to_exponent(pixel.jacobian_current[0].exponent);
-                        scale_factor_derivative = 1.0f64.ldexp(-pixel.jacobian_current[0].exponent);
+                        scale_factor_derivative = Perturbation::scaled_derivative_reference(pixel, julia);
                     }
 
                     scale_factor_delta = 1.0f64.ldexp(pixel.delta_current.exponent);
-                    scaled_delta_reference = 1.0f64.ldexp(pixel.delta_reference.exponent - pixel.delta_current.exponent) * pixel.delta_reference.mantissa;
+                    scaled_delta_reference = Perturbation::scaled_delta_reference(pixel, julia);
                 }
             }
 
@@ -1,7 +1,8 @@
-use rug::ops::PowAssign;
+use rug::ops::{NegAssign, PowAssign};
 
-use crate::util::{ComplexArbitrary, ComplexFixed, ComplexExtended, FloatExtended, to_fixed, to_extended, FloatArbitrary};
-use std::sync::{Arc, atomic::{AtomicUsize, AtomicBool, Ordering}};
+use crate::util::{ComplexArbitrary, ComplexFixed, ComplexExtended, FloatExtended, RenderHandle, to_fixed, to_extended, FloatArbitrary};
+use std::mem::size_of;
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 
 #[derive(Clone)]
 pub struct Reference {
@@ -12,68 +13,92 @@ pub struct Reference {
     pub c: ComplexArbitrary,
     pub reference_data: Vec<ComplexFixed<f64>>,
     pub extended_iterations: Vec<usize>,
+    // In compact mode this only has the values which are too small for the fixed precision data, at extended_indices
     pub reference_data_extended: Vec<ComplexExtended>,
+    pub extended_indices: Vec<usize>,
+    pub compact: bool,
     // This is for every 100th iteration, when we do glitch correction the new references will be spawed from these values
     // Storing every iteration is memory intensive.
     pub zoom: FloatExtended,
     pub data_storage_interval: usize,
     pub high_precision_data: Vec<ComplexArbitrary>,
     pub glitch_tolerance: f64,
+    // For Julia sets c is the fixed seed and the pixel offsets are added to the initial z instead
+    pub julia: bool,
+    // Set once the reference has escaped or reached the maximum iteration, a cancelled reference continues from where it stopped
+    pub complete: bool,
 }
 
 impl Reference {
-    pub fn new(_z: ComplexArbitrary, c: ComplexArbitrary, _current_iteration: usize, maximum_iteration: usize, data_storage_interval: usize, glitch_tolerance: f64, zoom: FloatExtended) -> Reference {
-        let zero = ComplexArbitrary::with_val(
-            c.prec().0 as u32,
-            ComplexArbitrary::parse("(0.0,0.0)").expect("provided location not valid"));
-
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(z: ComplexArbitrary, c: ComplexArbitrary, current_iteration: usize, maximum_iteration: usize, data_storage_interval: usize, glitch_tolerance: f64, zoom: FloatExtended, julia: bool) -> Reference {
         Reference {
-            start_iteration: 0,
-            current_iteration: 0,
+            start_iteration: current_iteration,
+            current_iteration,
             maximum_iteration,
-            z: zero,
+            z,
             c,
             reference_data: Vec::new(),
             extended_iterations: Vec::new(),
             reference_data_extended: Vec::new(),
+            extended_indices: Vec::new(),
+            compact: false,
             zoom,
             data_storage_interval,
             high_precision_data: Vec::new(),
-            glitch_tolerance
+            glitch_tolerance,
+            julia,
+            complete: false
         }
     }
 
-    pub fn run<const FRACTAL_TYPE: usize, const FRACTAL_POWER: usize>(&mut self, reference_counter: &Arc<AtomicUsize>, reference_maximum_iteration_counter: &Arc<AtomicUsize>, stop_flag: &Arc<AtomicBool>) {
-        let z_fixed = to_fixed(&self.z);
-        // let tolerance = self.glitch_tolerance * z_fixed.norm_sqr();
+    pub fn run<const FRACTAL_TYPE: usize, const FRACTAL_POWER: usize>(&mut self, reference_counter: &Arc<AtomicUsize>, reference_maximum_iteration_counter: &Arc<AtomicUsize>, handle: &RenderHandle) {
+        self.run_until::<FRACTAL_TYPE, FRACTAL_POWER>(self.maximum_iteration, reference_counter, reference_maximum_iteration_counter, handle);
+    }
 
-        // This is if we need to use the extended precision for the reference
-        // if z_fixed.re.abs() < 1e-300 && z_fixed.im.abs() < 1e-300 {
-        //     self.extended_iterations.push(self.current_iteration);
-        // }
+    // Runs the reference up to the end of the window, so that the orbit can be saved before it is continued
+    pub fn run_until<const FRACTAL_TYPE: usize, const FRACTAL_POWER: usize>(&mut self, window_end: usize, reference_counter: &Arc<AtomicUsize>, reference_maximum_iteration_counter: &Arc<AtomicUsize>, handle: &RenderHandle) {
+        if self.complete {
+            reference_counter.store(self.current_iteration, Ordering::SeqCst);
+            reference_maximum_iteration_counter.store(self.current_iteration, Ordering::SeqCst);
+            return;
+        }
 
-        // We pack these together as they are always accessed together
-        // The first iteration is z_1=c = iteration 1 is index 0
-        // access with iteration - start_iteration
-        self.reference_data.push(z_fixed);
+        // A cancelled reference already has the starting value stored
+        if self.reference_data.is_empty() {
+            let z_fixed = to_fixed(&self.z);
+            // let tolerance = self.glitch_tolerance * z_fixed.norm_sqr();
 
-        let mut z_extended = to_extended(&self.z);
-        z_extended.reduce();
+            // This is if we need to use the extended precision for the reference
+            // if z_fixed.re.abs() < 1e-300 && z_fixed.im.abs() < 1e-300 {
+            //     self.extended_iterations.push(self.current_iteration);
+            // }
 
-        self.reference_data_extended.push(z_extended);
+            // We pack these together as they are always accessed together
+            // The first iteration is z_1=c = iteration 1 is index 0
+            // access with iteration - start_iteration
+            self.push_reference_data(z_fixed);
+        }
 
-        while self.current_iteration < self.maximum_iteration {
-            if self.data_storage_interval == 1 || self.current_iteration % self.data_storage_interval == 1 {
-                self.high_precision_data.push(self.z.clone());
-            }
+        let window_end = window_end.min(self.maximum_iteration);
 
-            if stop_flag.load(Ordering::SeqCst) {
+        while self.current_iteration < window_end {
+            // This is checked before anything is stored, so that the reference can be continued
+            if handle.should_stop() {
                 return;
             };
 
+            if self.data_storage_interval == 1 || self.current_iteration % self.data_storage_interval == 1 {
+                self.high_precision_data.push(self.z.clone());
+            }
+
             reference_counter.fetch_add(1, Ordering::SeqCst);
 
+            // The first iteration of a Julia set is the location itself, so that iteration 1 is always the start of the pixel orbit
+            let julia_start = self.julia && self.current_iteration == 0;
+
             match FRACTAL_TYPE {
+                _ if julia_start => {},
                 1 => {
                     // Burning ship
                     self.z.mut_real().abs_mut();
@@ -82,6 +107,37 @@ impl Reference {
                     self.z.square_mut();
                     self.z += &self.c;
                 }
+                2 => {
+                    // Tricorn (Mandelbar)
+                    self.z.mut_imag().neg_assign();
+
+                    self.z.square_mut();
+                    self.z += &self.c;
+                }
+                3 => {
+                    // Celtic
+                    self.z.square_mut();
+                    self.z.mut_real().abs_mut();
+
+                    self.z += &self.c;
+                }
+                4 => {
+                    // Buffalo
+                    self.z.square_mut();
+                    self.z.mut_real().abs_mut();
+                    self.z.mut_imag().abs_mut();
+                    self.z.mut_imag().neg_assign();
+
+                    self.z += &self.c;
+                }
+                5 => {
+                    // Perpendicular burning ship
+                    self.z.mut_imag().abs_mut();
+                    self.z.mut_imag().neg_assign();
+
+                    self.z.square_mut();
+                    self.z += &self.c;
+                }
                 _ => {
                     match FRACTAL_POWER {
                         2 => {
@@ -109,17 +165,12 @@ impl Reference {
             // let tolerance = self.glitch_tolerance * z_fixed.norm_sqr();
     
             // This is if we need to use the extended precision for the reference
-            if z_fixed.re.abs() < 1e-300 && z_fixed.im.abs() < 1e-300 {
+            if Reference::underflows(z_fixed) {
                 // this is stored without the offset
                 self.extended_iterations.push(self.current_iteration);
             }
     
-            self.reference_data.push(z_fixed);
-    
-            let mut z_extended = to_extended(&self.z);
-            z_extended.reduce();
-    
-            self.reference_data_extended.push(z_extended);
+            self.push_reference_data(z_fixed);
     
             // If the value is not small we do the escape check, otherwise it has not escaped
             // as we do the check for 65536 on the perturbation, we need this to be more than that squared
@@ -128,19 +179,114 @@ impl Reference {
             }
         }
 
+        if !self.escaped() && self.current_iteration < self.maximum_iteration {
+            return;
+        }
+
+        // Glitch resolving references can be spawned from any iteration, including the last one
+        if self.data_storage_interval == 1 {
+            self.high_precision_data.push(self.z.clone());
+        }
+
+        self.complete = true;
+
         reference_maximum_iteration_counter.store(self.current_iteration, Ordering::SeqCst);
 
         // println!("{:?}", self.extended_iterations);
     }
 
+    // Continues a completed orbit to a higher maximum iteration when it is run again, an orbit that escaped is unchanged
+    pub fn extend(&mut self, maximum_iteration: usize) {
+        if maximum_iteration <= self.maximum_iteration {
+            return;
+        }
+
+        if self.complete && !self.escaped() {
+            // The last value is stored again once the extended orbit is complete
+            if self.data_storage_interval == 1 {
+                self.high_precision_data.pop();
+            }
+
+            self.complete = false;
+        }
+
+        self.maximum_iteration = maximum_iteration;
+    }
+
+    pub fn escaped(&self) -> bool {
+        self.reference_data.last().is_some_and(|z_fixed| z_fixed.norm_sqr() >= 1e256)
+    }
+
+    fn push_reference_data(&mut self, z_fixed: ComplexFixed<f64>) {
+        // Compact references only need the extended value when the fixed precision value underflows
+        if !self.compact || Reference::underflows(z_fixed) {
+            let mut z_extended = to_extended(&self.z);
+            z_extended.reduce();
+
+            if self.compact {
+                self.extended_indices.push(self.reference_data.len());
+            }
+
+            self.reference_data_extended.push(z_extended);
+        }
+
+        self.reference_data.push(z_fixed);
+    }
+
+    #[inline]
+    fn underflows(z_fixed: ComplexFixed<f64>) -> bool {
+        z_fixed.re.abs() < 1e-300 && z_fixed.im.abs() < 1e-300
+    }
+
+    // The extended precision value at an index of the reference data
+    #[inline]
+    pub fn reference_extended(&self, index: usize) -> ComplexExtended {
+        if !self.compact {
+            return self.reference_data_extended[index];
+        }
+
+        let z_fixed = self.reference_data[index];
+
+        if Reference::underflows(z_fixed) {
+            if let Ok(extended_index) = self.extended_indices.binary_search(&index) {
+                return self.reference_data_extended[extended_index];
+            }
+        }
+
+        let mut z_extended = ComplexExtended::new(z_fixed, 0);
+        z_extended.reduce();
+        z_extended
+    }
+
+    // The approximate number of bytes used to store the orbit
+    pub fn memory_usage(&self) -> usize {
+        let high_precision_size = self.high_precision_data.first().map_or(0, |value| {
+            // Each part has the limbs for the precision as well as the float itself
+            2 * (size_of::<FloatArbitrary>() + (value.prec().0 as usize).div_ceil(64) * 8)
+        });
+
+        self.reference_data.capacity() * size_of::<ComplexFixed<f64>>()
+            + self.reference_data_extended.capacity() * size_of::<ComplexExtended>()
+            + (self.extended_indices.capacity() + self.extended_iterations.capacity()) * size_of::<usize>()
+            + self.high_precision_data.capacity() * high_precision_size
+    }
+
     // This gets a reference that stores the high precision data every iteration
     pub fn get_central_glitch_resolving_reference(&self, iteration: usize) -> Reference {
-        let iteration_reference = self.data_storage_interval * ((iteration - self.start_iteration) / self.data_storage_interval) + self.start_iteration;
+        // The central reference stores high precision data on iterations 1, 101, 201 etc.
+        let (storage_index, iteration_reference) = if self.data_storage_interval == 1 {
+            (iteration - self.start_iteration, iteration)
+        } else {
+            let storage_index = (iteration - 1) / self.data_storage_interval;
+            (storage_index, storage_index * self.data_storage_interval + 1)
+        };
 
         let reference_c = self.c.clone();
-        let reference_z = self.high_precision_data[(iteration - self.start_iteration) / self.data_storage_interval].clone();
+        let reference_z = self.high_precision_data[storage_index].clone();
 
-        Reference::new(reference_z, reference_c, iteration_reference, self.maximum_iteration, 1, self.glitch_tolerance, self.zoom)
+        let mut reference = Reference::new(reference_z, reference_c, iteration_reference, self.maximum_iteration, 1, self.glitch_tolerance, self.zoom, self.julia);
+        reference.compact = self.compact;
+        reference
     }
 
     // This is for use when getting new references others with full data
@@ -151,12 +297,15 @@ impl Reference {
 
         let mut reference_c = self.c.clone();
 
-        let temp = FloatArbitrary::with_val(precision, reference_delta.exponent).exp2();
-        let temp2 = FloatArbitrary::with_val(precision, reference_delta.mantissa.re);
-        let temp3 = FloatArbitrary::with_val(precision, reference_delta.mantissa.im);
+        // Julia sets share the same c for every pixel
+        if !self.julia {
+            let temp = FloatArbitrary::with_val(precision, reference_delta.exponent).exp2();
+            let temp2 = FloatArbitrary::with_val(precision, reference_delta.mantissa.re);
+            let temp3 = FloatArbitrary::with_val(precision, reference_delta.mantissa.im);
 
-        *reference_c.mut_real() += &temp2 * &temp;
-        *reference_c.mut_imag() += &temp3 * &temp;
+            *reference_c.mut_real() += &temp2 * &temp;
+            *reference_c.mut_imag() += &temp3 * &temp;
+        }
 
         let mut reference_z = self.high_precision_data[iteration - self.start_iteration].clone();
 
@@ -167,7 +316,9 @@ impl Reference {
         *reference_z.mut_real() += &temp2 * &temp;
         *reference_z.mut_imag() += &temp3 * &temp;
 
-        Reference::new(reference_z, reference_c, iteration, self.maximum_iteration, 1, self.glitch_tolerance, self.zoom)
+        let mut reference = Reference::new(reference_z, reference_c, iteration, self.maximum_iteration, 1, self.glitch_tolerance, self.zoom, self.julia);
+        reference.compact = self.compact;
+        reference
     }
 }
 
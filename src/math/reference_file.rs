@@ -0,0 +1,360 @@
+use crate::Error;
+use crate::math::{Reference, SeriesApproximation};
+use crate::util::{ComplexArbitrary, ComplexExtended, ComplexFixed, FloatArbitrary, FractalType};
+
+use std::convert::TryInto;
+use std::fs;
+use std::io::ErrorKind;
+
+const MAGIC: &[u8; 8] = b"RFREFORB";
+
+// Increased whenever the layout changes. Loading a file with another version gives an InvalidReference error, after which
+// the renderer gives a warning and calculates the orbit again
+const VERSION: u32 = 1;
+
+// Identifies the orbit that a reference file was calculated for, so that it is only reused for the same location
+// This needs to be created before the reference is run, as running the reference replaces the starting z
+// Incomplete references can also be saved as checkpoints, which are continued when they are loaded
+pub struct ReferenceFile {
+    pub path: String,
+    fractal_type: FractalType,
+    julia: bool,
+    maximum_iteration: usize,
+    data_storage_interval: usize,
+    compact: bool,
+    start_z: ComplexArbitrary,
+    c: ComplexArbitrary,
+}
+
+impl ReferenceFile {
+    pub fn new(path: &str, reference: &Reference, fractal_type: FractalType) -> Self {
+        ReferenceFile {
+            path: path.to_owned(),
+            fractal_type,
+            julia: reference.julia,
+            maximum_iteration: reference.maximum_iteration,
+            data_storage_interval: reference.data_storage_interval,
+            compact: reference.compact,
+            start_z: reference.z.clone(),
+            c: reference.c.clone(),
+        }
+    }
+
+    // Writes the reference, and the series approximation coefficients if they have been generated
+    pub fn save(&self, reference: &Reference, series_approximation: Option<&SeriesApproximation>) -> Result<(), Error> {
+        let mut output = Vec::new();
+
+        output.extend_from_slice(MAGIC);
+        write_u32(&mut output, VERSION);
+
+        let (fractal_index, fractal_power) = fractal_type_to_index(self.fractal_type);
+
+        write_u32(&mut output, fractal_index);
+        write_u32(&mut output, fractal_power);
+        output.push(self.julia as u8);
+        write_usize(&mut output, self.data_storage_interval);
+        output.push(self.compact as u8);
+
+        // The starting values are stored at the precision they were given, and the orbit at the precision of the reference
+        write_u32(&mut output, self.c.prec().0);
+        write_complex(&mut output, &self.start_z);
+        write_complex(&mut output, &self.c);
+
+        write_u32(&mut output, reference.c.prec().0);
+        write_usize(&mut output, reference.maximum_iteration);
+        output.push(reference.complete as u8);
+        write_usize(&mut output, reference.start_iteration);
+        write_usize(&mut output, reference.current_iteration);
+        write_complex(&mut output, &reference.z);
+        write_complex(&mut output, &reference.c);
+
+        write_usize(&mut output, reference.reference_data.len());
+
+        for value in &reference.reference_data {
+            write_f64(&mut output, value.re);
+            write_f64(&mut output, value.im);
+        }
+
+        write_usize(&mut output, reference.reference_data_extended.len());
+
+        for value in &reference.reference_data_extended {
+            write_complex_extended(&mut output, value);
+        }
+
+        write_usize(&mut output, reference.extended_indices.len());
+
+        for &index in &reference.extended_indices {
+            write_usize(&mut output, index);
+        }
+
+        write_usize(&mut output, reference.extended_iterations.len());
+
+        for &iteration in &reference.extended_iterations {
+            write_usize(&mut output, iteration);
+        }
+
+        write_usize(&mut output, reference.high_precision_data.len());
+
+        for value in &reference.high_precision_data {
+            write_complex(&mut output, value);
+        }
+
+        // An order of 0 means that there are no coefficients stored
+        let series_approximation = series_approximation.filter(|series_approximation| series_approximation.enabled && series_approximation.generated_order > 0);
+
+        match series_approximation {
+            Some(series_approximation) => {
+                write_usize(&mut output, series_approximation.generated_order);
+                write_usize(&mut output, series_approximation.coefficients.len());
+                write_usize(&mut output, series_approximation.coefficients[0].len());
+
+                for coefficients in &series_approximation.coefficients {
+                    for coefficient in coefficients {
+                        write_complex_extended(&mut output, coefficient);
+                    }
+                }
+            },
+            None => write_usize(&mut output, 0)
+        }
+
+        // The file is replaced in one step, so that the previous checkpoint is kept if the write is interrupted
+        let temporary_path = self.path.clone() + ".tmp";
+
+        fs::write(&temporary_path, output)
+            .and_then(|_| fs::rename(&temporary_path, &self.path))
+            .map_err(|error| Error::Write(self.path.clone(), Box::new(error)))
+    }
+
+    // Loads the reference if the file was written for the same orbit, returning false if it needs to be calculated
+    // A checkpoint or an orbit with a lower maximum iteration is loaded incomplete, so that running the reference continues it
+    // The series approximation is only replaced if the orbit is complete and the coefficients were stored with the same order
+    pub fn load(&self, reference: &mut Reference, series_approximation: &mut SeriesApproximation) -> Result<bool, Error> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(Error::InvalidReference(format!("{}: {}", self.path, error)))
+        };
+
+        let mut input = ByteReader::new(&bytes, &self.path);
+
+        if input.read_bytes(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidReference(format!("{} is not a reference file", self.path)));
+        }
+
+        let version = input.read_u32()?;
+
+        if version != VERSION {
+            return Err(Error::InvalidReference(format!("{} has version {}, expected {}", self.path, version, VERSION)));
+        }
+
+        let fractal_type = (input.read_u32()?, input.read_u32()?);
+        let julia = input.read_u8()? != 0;
+        let data_storage_interval = input.read_usize()?;
+        let compact = input.read_u8()? != 0;
+
+        if fractal_type != fractal_type_to_index(self.fractal_type)
+            || julia != self.julia
+            || data_storage_interval != self.data_storage_interval
+            || compact != self.compact {
+            return Ok(false);
+        }
+
+        // A file calculated at a higher precision can be used for the same location at a lower zoom
+        let location_precision = input.read_u32()?;
+        let start_z = input.read_complex(location_precision)?;
+        let c = input.read_complex(location_precision)?;
+
+        let precision = self.c.prec().0;
+
+        if location_precision < precision
+            || ComplexArbitrary::with_val(precision, &start_z) != self.start_z
+            || ComplexArbitrary::with_val(precision, &c) != self.c {
+            return Ok(false);
+        }
+
+        let reference_precision = input.read_u32()?;
+        let maximum_iteration = input.read_usize()?;
+        let complete = input.read_u8()? != 0;
+        let start_iteration = input.read_usize()?;
+        let current_iteration = input.read_usize()?;
+        let z = input.read_complex(reference_precision)?;
+        let reference_c = input.read_complex(reference_precision)?;
+
+        let reference_data = (0..input.read_usize()?).map(|_| {
+            Ok(ComplexFixed::new(input.read_f64()?, input.read_f64()?))
+        }).collect::<Result<Vec<ComplexFixed<f64>>, Error>>()?;
+
+        let reference_data_extended = (0..input.read_usize()?)
+            .map(|_| input.read_complex_extended())
+            .collect::<Result<Vec<ComplexExtended>, Error>>()?;
+
+        let extended_indices = (0..input.read_usize()?)
+            .map(|_| input.read_usize())
+            .collect::<Result<Vec<usize>, Error>>()?;
+
+        let extended_iterations = (0..input.read_usize()?)
+            .map(|_| input.read_usize())
+            .collect::<Result<Vec<usize>, Error>>()?;
+
+        let high_precision_data = (0..input.read_usize()?)
+            .map(|_| input.read_complex(reference_precision))
+            .collect::<Result<Vec<ComplexArbitrary>, Error>>()?;
+
+        let coefficient_order = input.read_usize()?;
+
+        let coefficients = if coefficient_order > 0 {
+            let iteration_count = input.read_usize()?;
+            let coefficient_count = input.read_usize()?;
+
+            (0..iteration_count).map(|_| {
+                (0..coefficient_count)
+                    .map(|_| input.read_complex_extended())
+                    .collect::<Result<Vec<ComplexExtended>, Error>>()
+            }).collect::<Result<Vec<Vec<ComplexExtended>>, Error>>()?
+        } else {
+            Vec::new()
+        };
+
+        // Any orbit that has not gone past the maximum iteration is the start of the orbit that is needed
+        if current_iteration > self.maximum_iteration {
+            return Ok(false);
+        }
+
+        // Everything is read before the reference is modified, so that a truncated file leaves it unchanged
+        reference.start_iteration = start_iteration;
+        reference.current_iteration = current_iteration;
+        reference.z = z;
+        reference.c = reference_c;
+        reference.reference_data = reference_data;
+        reference.reference_data_extended = reference_data_extended;
+        reference.extended_indices = extended_indices;
+        reference.extended_iterations = extended_iterations;
+        reference.high_precision_data = high_precision_data;
+        reference.maximum_iteration = maximum_iteration.min(self.maximum_iteration);
+        reference.complete = complete;
+        reference.extend(self.maximum_iteration);
+
+        series_approximation.maximum_iteration = current_iteration;
+
+        if reference.complete && series_approximation.enabled && coefficient_order == series_approximation.order {
+            series_approximation.coefficients = coefficients;
+            series_approximation.generated_order = coefficient_order;
+        } else {
+            series_approximation.generated_order = 0;
+        }
+
+        Ok(true)
+    }
+}
+
+fn fractal_type_to_index(fractal_type: FractalType) -> (u32, u32) {
+    let index = match fractal_type {
+        FractalType::Mandelbrot(_) => 0,
+        FractalType::BurningShip(_) => 1,
+        FractalType::Tricorn(_) => 2,
+        FractalType::Celtic(_) => 3,
+        FractalType::Buffalo(_) => 4,
+        FractalType::PerpendicularBurningShip(_) => 5,
+    };
+
+    (index, fractal_type.power() as u32)
+}
+
+fn write_u32(output: &mut Vec<u8>, value: u32) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_usize(output: &mut Vec<u8>, value: usize) {
+    output.extend_from_slice(&(value as u64).to_le_bytes());
+}
+
+fn write_f64(output: &mut Vec<u8>, value: f64) {
+    output.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_complex_extended(output: &mut Vec<u8>, value: &ComplexExtended) {
+    write_f64(output, value.mantissa.re);
+    write_f64(output, value.mantissa.im);
+    output.extend_from_slice(&value.exponent.to_le_bytes());
+}
+
+// Arbitrary precision values are stored as exact hexadecimal strings
+fn write_complex(output: &mut Vec<u8>, value: &ComplexArbitrary) {
+    for part in [value.real(), value.imag()] {
+        let string = format!("{:x}", part);
+
+        write_u32(output, string.len() as u32);
+        output.extend_from_slice(string.as_bytes());
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    path: &'a str,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8], path: &'a str) -> Self {
+        ByteReader {
+            bytes,
+            position: 0,
+            path,
+        }
+    }
+
+    fn invalid(&self, reason: &str) -> Error {
+        Error::InvalidReference(format!("{}: {} at byte {}", self.path, reason, self.position))
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() - self.position < length {
+            return Err(self.invalid("unexpected end of file"));
+        }
+
+        let bytes = &self.bytes[self.position..(self.position + length)];
+        self.position += length;
+
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, Error> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()) as usize)
+    }
+
+    fn read_f64(&mut self) -> Result<f64, Error> {
+        Ok(f64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_complex_extended(&mut self) -> Result<ComplexExtended, Error> {
+        let re = self.read_f64()?;
+        let im = self.read_f64()?;
+        let exponent = i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap());
+
+        Ok(ComplexExtended::new2(re, im, exponent))
+    }
+
+    fn read_float(&mut self, precision: u32) -> Result<FloatArbitrary, Error> {
+        let length = self.read_u32()? as usize;
+        let string = std::str::from_utf8(self.read_bytes(length)?).map_err(|_| self.invalid("value is not valid text"))?;
+
+        let parsed = FloatArbitrary::parse_radix(string, 16).map_err(|_| self.invalid("value is not a number"))?;
+
+        Ok(FloatArbitrary::with_val(precision, parsed))
+    }
+
+    fn read_complex(&mut self, precision: u32) -> Result<ComplexArbitrary, Error> {
+        let re = self.read_float(precision)?;
+        let im = self.read_float(precision)?;
+
+        Ok(ComplexArbitrary::with_val(precision, (re, im)))
+    }
+}
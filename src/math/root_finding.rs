@@ -2,14 +2,14 @@ use std::{cmp::max};
 use std::time::Instant;
 
 use std::sync::Arc;
-use std::sync::atomic::{Ordering, AtomicUsize, AtomicBool};
+use std::sync::atomic::{Ordering, AtomicUsize};
 
 // use std::thread;
 // use std::time::Duration;
 
 use parking_lot::Mutex;
 
-use crate::util::{ComplexArbitrary, ComplexExtended, FloatArbitrary, FloatExtended, to_extended};
+use crate::util::{ComplexArbitrary, ComplexExtended, FloatArbitrary, FloatExtended, RenderHandle, to_extended};
 use crate::math::Reference;
 
 pub struct BoxPeriod {
@@ -63,8 +63,9 @@ impl BoxPeriod {
     }
 
     pub fn find_period(&mut self, reference: &Reference) {
+        // The reference data starts at z_0, so the box at the period is compared with the reference at the same index
         while self.period < reference.current_iteration {
-            if self.points_surrond_origin(reference.reference_data_extended[self.period - 1]) {
+            if self.points_surrond_origin(reference.reference_extended(self.period)) {
                 // do some calculation here to work out a good estimate for the starting point
                 // println!("{} {} {} {}", self.points_z[0], self.points_z[1], self.points_z[2], self.points_z[3]);
 
@@ -74,7 +75,7 @@ impl BoxPeriod {
 
             // TODO maybe add some glitch tests?
             for i in 0..4 {
-                self.points_z[i] = self.points_z[i] * (reference.reference_data_extended[self.period - 1] * 2.0 + self.points_z[i]);
+                self.points_z[i] = self.points_z[i] * (reference.reference_extended(self.period) * 2.0 + self.points_z[i]);
                 self.points_z[i] += self.points_c[i];
                 self.points_z[i].reduce();
             }
@@ -83,18 +84,19 @@ impl BoxPeriod {
         }
     }
 
+    // The atom domain period is the iteration where |z| of the box centre is smallest
     pub fn find_atom_domain_period(&mut self, reference: &Reference) {
         let mut z = self.box_center;
 
-        let mut min_value = (reference.reference_data_extended[0] + z).norm();
-        let mut min_value_at = 0;
+        let mut min_value = (reference.reference_extended(1) + z).norm();
+        let mut min_value_at = 1;
 
         for i in 2..reference.current_iteration {
-            z *= reference.reference_data_extended[i - 1] * 2.0 + self.box_center;
+            z *= reference.reference_extended(i - 1) * 2.0 + z;
             z += self.box_center;
             z.reduce();
 
-            let distance_from_origin = (reference.reference_data_extended[i] + z).norm();
+            let distance_from_origin = (reference.reference_extended(i) + z).norm();
 
             // point has escaped
             if distance_from_origin.to_float() > 1e16 {
@@ -107,7 +109,7 @@ impl BoxPeriod {
             }
         }
 
-        println!("atom domain period is: {}", min_value_at);
+        self.period = min_value_at;
     }
 }
 
@@ -147,7 +149,7 @@ impl BallMethod {
             self.radius_dz = self.point_dz.norm();
             self.radius_dz.reduce();
 
-            self.radius_z = (self.point_z + reference.reference_data_extended[k]).norm();
+            self.radius_z = (self.point_z + reference.reference_extended(k)).norm();
             self.radius_z.reduce();
 
             self.radius_radius = self.radius * (self.radius_dz + self.radius * self.ei);
@@ -162,11 +164,11 @@ impl BallMethod {
             self.ei = self.radius_dz * self.radius_dz + (2.0 * self.radius_z + self.radius * (2.0 * self.radius_dz + self.radius * self.ei)) * self.ei;
             self.ei.reduce();
 
-            self.point_dz *= (self.point_z + reference.reference_data_extended[k]) * 2.0;
+            self.point_dz *= (self.point_z + reference.reference_extended(k)) * 2.0;
             self.point_dz += ComplexExtended::new2(1.0, 0.0, 0);
             self.point_dz.reduce();
 
-            self.point_z *= reference.reference_data_extended[k] * 2.0 + self.point_z;
+            self.point_z *= reference.reference_extended(k) * 2.0 + self.point_z;
             self.point_z += self.point_c;
             self.point_z.reduce();
         }
@@ -175,7 +177,8 @@ impl BallMethod {
     }
 }
 
-pub fn get_nucleus(mut guess_c: ComplexArbitrary, period: usize, iteration_flag: Arc<AtomicUsize>, progress_flag: Arc<AtomicUsize>, stop_flag: Arc<AtomicBool>, current_estimate: Arc<Mutex<ComplexExtended>>) -> Option<ComplexArbitrary> {
+// Returns None if the method does not converge or the handle is cancelled
+pub fn get_nucleus(mut guess_c: ComplexArbitrary, period: usize, iteration_flag: Arc<AtomicUsize>, progress_flag: Arc<AtomicUsize>, handle: &RenderHandle, current_estimate: Arc<Mutex<ComplexExtended>>) -> Option<ComplexArbitrary> {
     let complex_precision = guess_c.prec();
     let precision = 3 * max(complex_precision.0, complex_precision.1);
 
@@ -205,15 +208,14 @@ pub fn get_nucleus(mut guess_c: ComplexArbitrary, period: usize, iteration_flag:
             z_current.square_mut();
             z_current += &guess_c;
     
-            if i < period && period % i == 0 {
+            if i < period && period.is_multiple_of(i) {
                 h *= &z_current;
                 dh += derivative_current.clone() / &z_current;
             }
 
             progress_flag.fetch_add(1, Ordering::SeqCst);
 
-            if stop_flag.load(Ordering::SeqCst) {
-                stop_flag.store(false, Ordering::SeqCst);
+            if handle.should_stop() {
                 return None
             };
         }
@@ -291,3 +293,180 @@ pub fn get_nucleus_position(nucleus: ComplexArbitrary, period: usize) -> (FloatE
 
     (zoom, size.mantissa.arg())
 }
+
+// Finds the preperiod and period of Misiurewicz points near the reference, where the orbit of the point lands on a repelling cycle
+pub struct MisiurewiczPeriod {
+    pub radius: FloatExtended,
+    derivatives: Vec<ComplexExtended>,
+}
+
+impl MisiurewiczPeriod {
+    pub fn new(radius: FloatExtended) -> Self {
+        MisiurewiczPeriod {
+            radius,
+            derivatives: Vec::new(),
+        }
+    }
+
+    // Returns a preperiod for each period up to the maximum, where one Newton step from the reference stays within the radius
+    // The candidates are ordered by period, and the lowest preperiod is used for each period as later ones also land on the cycle
+    pub fn find_candidates(&mut self, reference: &Reference, maximum_iteration: usize) -> Vec<(usize, usize)> {
+        let end = reference.current_iteration.min(maximum_iteration);
+
+        // Derivatives of the orbit with respect to c
+        self.derivatives.clear();
+        self.derivatives.push(ComplexExtended::new2(0.0, 0.0, 0));
+
+        for i in 1..=end {
+            let mut derivative = self.derivatives[i - 1] * reference.reference_extended(i - 1) * 2.0 + ComplexExtended::new2(1.0, 0.0, 0);
+            derivative.reduce();
+
+            self.derivatives.push(derivative);
+        }
+
+        let mut candidates = Vec::new();
+
+        for period in 1..end {
+            for preperiod in 1..(end - period) {
+                let mut difference = reference.reference_extended(preperiod + period) - reference.reference_extended(preperiod);
+                difference.reduce();
+
+                let mut difference_derivative = self.derivatives[preperiod + period] - self.derivatives[preperiod];
+                difference_derivative.reduce();
+
+                if difference_derivative.mantissa.norm_sqr() == 0.0 {
+                    continue;
+                }
+
+                let mut step = difference / difference_derivative;
+                step.reduce();
+
+                if step.norm() < self.radius {
+                    candidates.push((preperiod, period));
+                    break;
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+// Solves f^(preperiod + period)(c) = f^preperiod(c) with Newton's method, removing the roots with lower preperiods and periods
+// Returns None if the method does not converge or the handle is cancelled
+pub fn get_misiurewicz(mut guess_c: ComplexArbitrary, preperiod: usize, period: usize, handle: &RenderHandle) -> Option<ComplexArbitrary> {
+    let complex_precision = guess_c.prec();
+    let precision = 3 * max(complex_precision.0, complex_precision.1);
+
+    guess_c.set_prec(precision);
+
+    let temp = FloatArbitrary::with_val(precision, 2);
+    let mut epsilon = temp.clone();
+
+    epsilon.next_up();
+    epsilon -= &temp;
+
+    let epsilon_squared = epsilon.square();
+
+    let step = |z: &mut ComplexArbitrary, derivative: &mut ComplexArbitrary, c: &ComplexArbitrary| {
+        *derivative *= 2.0;
+        *derivative *= &*z;
+        *derivative += 1.0;
+
+        z.square_mut();
+        *z += c;
+    };
+
+    for _ in 0..64 {
+        // The orbit at i and i + period are iterated together
+        let mut z_lower = ComplexArbitrary::new(precision);
+        let mut derivative_lower = ComplexArbitrary::new(precision);
+        let mut z_upper = ComplexArbitrary::new(precision);
+        let mut derivative_upper = ComplexArbitrary::new(precision);
+
+        for _ in 0..period {
+            step(&mut z_upper, &mut derivative_upper, &guess_c);
+
+            if handle.should_stop() {
+                return None;
+            }
+        }
+
+        // Sum of the logarithmic derivatives of the removed factors
+        let mut divisor_derivative = ComplexArbitrary::new(precision);
+
+        for _ in 0..preperiod {
+            let factor = z_upper.clone() - &z_lower;
+            divisor_derivative += (derivative_upper.clone() - &derivative_lower) / &factor;
+
+            step(&mut z_lower, &mut derivative_lower, &guess_c);
+            step(&mut z_upper, &mut derivative_upper, &guess_c);
+
+            if handle.should_stop() {
+                return None;
+            }
+        }
+
+        let value = z_upper.clone() - &z_lower;
+        let value_derivative = derivative_upper.clone() - &derivative_lower;
+
+        // Cycles with a period that divides the period are also removed
+        let mut z_cycle = z_lower.clone();
+        let mut derivative_cycle = derivative_lower.clone();
+
+        for i in 1..period {
+            step(&mut z_cycle, &mut derivative_cycle, &guess_c);
+
+            if period.is_multiple_of(i) {
+                let factor = z_cycle.clone() - &z_lower;
+                divisor_derivative += (derivative_cycle.clone() - &derivative_lower) / &factor;
+            }
+
+            if handle.should_stop() {
+                return None;
+            }
+        }
+
+        let new_c = guess_c.clone() - value.clone() / (value_derivative - value * divisor_derivative);
+
+        let difference_norm = (new_c.clone() - &guess_c).norm();
+
+        if difference_norm.real() <= &epsilon_squared {
+            return Some(new_c);
+        } else if difference_norm.real().is_infinite() || difference_norm.real().is_nan() {
+            return None;
+        }
+
+        guess_c = new_c;
+    }
+
+    None
+}
+
+// Returns the size of the features around the point, from the inverse of the derivative of the orbit when it lands on the cycle,
+// and the multiplier of the cycle, which is the scaling and rotation between each repetition of the features
+pub fn get_misiurewicz_position(point: ComplexArbitrary, preperiod: usize, period: usize) -> (ComplexExtended, ComplexExtended) {
+    let mut z = ComplexArbitrary::new(point.prec());
+    let mut derivative = ComplexExtended::new2(0.0, 0.0, 0);
+    let mut multiplier = ComplexExtended::new2(1.0, 0.0, 0);
+
+    for i in 0..(preperiod + period) {
+        let z_extended = to_extended(&z);
+
+        if i < preperiod {
+            derivative = derivative * z_extended * 2.0 + ComplexExtended::new2(1.0, 0.0, 0);
+            derivative.reduce();
+        } else {
+            multiplier *= z_extended * 2.0;
+            multiplier.reduce();
+        }
+
+        z.square_mut();
+        z += &point;
+    }
+
+    let mut size = ComplexExtended::new2(1.0, 0.0, 0) / derivative;
+    size.reduce();
+
+    (size, multiplier)
+}
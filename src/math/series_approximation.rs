@@ -1,12 +1,16 @@
-use crate::util::{ComplexFixed, to_extended};
+use crate::util::{ComplexFixed, FractalType, RenderHandle, generate_pascal_coefficients};
 use crate::util::complex_extended::ComplexExtended;
 use crate::math::reference::Reference;
+use crate::math::Perturbation;
 use crate::util::float_extended::FloatExtended;
 use rayon::prelude::*;
 
 use std::cmp::max;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// The bivariate series grows with the square of the order, so this is limited
+const BIVARIATE_MAXIMUM_ORDER: usize = 8;
 
 pub struct SeriesApproximation {
     pub maximum_iteration: usize,
@@ -26,9 +30,12 @@ pub struct SeriesApproximation {
     pub enabled: bool,
     pub valid_iteration_probe_multiplier: f32,
     pub data_storage_interval: usize,
+    pub fractal_type: FractalType,
+    pascal: Vec<f64>,
 }
 
 impl SeriesApproximation {
+    #[allow(clippy::too_many_arguments)]
     pub fn new_central(order: usize, 
         maximum_iteration: usize, 
         delta_pixel_square: FloatExtended, 
@@ -36,7 +43,8 @@ impl SeriesApproximation {
         tiled: bool, 
         enabled: bool,
         valid_iteration_probe_multiplier: f32,
-        data_storage_interval: usize) -> Self {
+        data_storage_interval: usize,
+        fractal_type: FractalType) -> Self {
 
         // The current iteration is set to 1 as we set z = c
         SeriesApproximation {
@@ -56,11 +64,13 @@ impl SeriesApproximation {
             tiled,
             enabled,
             valid_iteration_probe_multiplier,
-            data_storage_interval
+            data_storage_interval,
+            fractal_type,
+            pascal: generate_pascal_coefficients(fractal_type.power() + 1)
         }
     }
 
-    pub fn generate_approximation(&mut self, center_reference: &Reference, series_approximation_counter: &Arc<AtomicUsize>, stop_flag: &Arc<AtomicBool>) {
+    pub fn generate_approximation(&mut self, center_reference: &Reference, series_approximation_counter: &Arc<AtomicUsize>, handle: &RenderHandle) {
         if !self.enabled {
             series_approximation_counter.store(1, Ordering::SeqCst);
             return;
@@ -68,47 +78,83 @@ impl SeriesApproximation {
 
         series_approximation_counter.store(0, Ordering::SeqCst);
 
+        // A cancelled generation leaves this unset, so that the approximation is generated again
+        self.generated_order = 0;
+
+        let bivariate = !self.fractal_type.holomorphic();
+
+        if bivariate {
+            self.order = self.order.min(BIVARIATE_MAXIMUM_ORDER);
+        }
+
+        let coefficient_count = self.coefficient_count();
+
         // Reset the coefficients
-        self.coefficients = vec![vec![ComplexExtended::new2(0.0, 0.0, 0); self.order as usize + 1]; 1];
+        self.coefficients = vec![vec![ComplexExtended::new2(0.0, 0.0, 0); coefficient_count]; 1];
 
-        // 1th element is the z^2 + c, which is the 1st iteration
-        self.coefficients[0][0] = to_extended(&center_reference.c);
+        // 1th element is the z^2 + c, which is the 1st iteration, or the location for Julia sets
+        self.coefficients[0][0] = center_reference.reference_extended(1);
         self.coefficients[0][1] = ComplexExtended::new2(1.0, 0.0, 0);
 
-        let add_value = ComplexExtended::new2(1.0, 0.0, 0);
+        // The bivariate series has separate terms for the real and imaginary parts of the delta
+        if bivariate {
+            self.coefficients[0][2] = ComplexExtended::new2(0.0, 1.0, 0);
+        }
 
         let mut previous_coefficients = self.coefficients[0].clone();
-        let mut next_coefficients = vec![ComplexExtended::new2(0.0, 0.0, 0); self.order as usize + 1];
+        let mut next_coefficients = vec![ComplexExtended::new2(0.0, 0.0, 0); coefficient_count];
 
         // Can be changed later into a better loop - this function could also return some more information
         // Go through all remaining iterations
         for i in 1..(self.maximum_iteration - 1) {
-            if stop_flag.load(Ordering::SeqCst) {
+            if handle.should_stop() {
                 return
             };
 
             // This is checking if the approximation can step forward so takes the next iteration
-            next_coefficients[0] = center_reference.reference_data_extended[i + 1];
-            next_coefficients[1] = previous_coefficients[0] * previous_coefficients[1] * 2.0 + add_value;
+            next_coefficients[0] = center_reference.reference_extended(i + 1);
             next_coefficients[0].reduce();
-            next_coefficients[1].reduce();
 
-            // Calculate the new coefficents
-            for k in 2..=self.order {
-                let mut sum = previous_coefficients[0] * previous_coefficients[k];
+            match self.fractal_type {
+                _ if bivariate => {
+                    self.next_coefficients_bivariate(&previous_coefficients, &mut next_coefficients);
+                },
+                FractalType::Mandelbrot(_) if self.fractal_type.power() != 2 => {
+                    self.next_coefficients_power(&previous_coefficients, &mut next_coefficients);
+                },
+                _ => {
+                    next_coefficients[1] = previous_coefficients[0] * previous_coefficients[1] * 2.0;
+                    next_coefficients[1].reduce();
+
+                    // Calculate the new coefficents
+                    for k in 2..=self.order {
+                        let mut sum = previous_coefficients[0] * previous_coefficients[k];
+
+                        for j in 1..=((k - 1) / 2) {
+                            sum += previous_coefficients[j] * previous_coefficients[k - j];
+                        }
+                        sum *= 2.0;
 
-                for j in 1..=((k - 1) / 2) {
-                    sum += previous_coefficients[j] * previous_coefficients[k - j];
-                }
-                sum *= 2.0;
+                        // If even, we include the mid term as well
+                        if k % 2 == 0 {
+                            sum += previous_coefficients[k / 2] * previous_coefficients[k / 2];
+                        }
 
-                // If even, we include the mid term as well
-                if k % 2 == 0 {
-                    sum += previous_coefficients[k / 2] * previous_coefficients[k / 2];
+                        sum.reduce();
+                        next_coefficients[k] = sum;
+                    }
                 }
+            }
 
-                sum.reduce();
-                next_coefficients[k] = sum;
+            // The delta of c is added to the linear terms, Julia sets only perturb the initial z
+            if !center_reference.julia {
+                next_coefficients[1] += ComplexExtended::new2(1.0, 0.0, 0);
+                next_coefficients[1].reduce();
+
+                if bivariate {
+                    next_coefficients[2] += ComplexExtended::new2(0.0, 1.0, 0);
+                    next_coefficients[2].reduce();
+                }
             }
 
             previous_coefficients = next_coefficients.clone();
@@ -128,6 +174,146 @@ impl SeriesApproximation {
         self.generated_order = self.order;
     }
 
+    // The number of coefficients, including the reference value stored at index 0
+    fn coefficient_count(&self) -> usize {
+        if self.fractal_type.holomorphic() {
+            self.order + 1
+        } else {
+            (self.order + 1) * (self.order + 2) / 2
+        }
+    }
+
+    // Index of the u^i * v^j term in the bivariate series, ordered by total degree
+    #[inline]
+    fn bivariate_index(i: usize, j: usize) -> usize {
+        let degree = i + j;
+        degree * (degree + 1) / 2 + j
+    }
+
+    // Truncated product of two series in the delta
+    fn multiply_series(a: &[ComplexExtended], b: &[ComplexExtended], order: usize) -> Vec<ComplexExtended> {
+        (0..=order).map(|k| {
+            let mut sum = a[0] * b[k];
+
+            for j in 1..=k {
+                sum += a[j] * b[k - j];
+            }
+
+            sum.reduce();
+            sum
+        }).collect()
+    }
+
+    // Truncated product of two bivariate series in the real and imaginary parts of the delta
+    fn multiply_bivariate(a: &[ComplexExtended], b: &[ComplexExtended], order: usize) -> Vec<ComplexExtended> {
+        let mut output = vec![ComplexExtended::new2(0.0, 0.0, 0); a.len()];
+
+        for degree in 0..=order {
+            for j in 0..=degree {
+                let i = degree - j;
+
+                // The sum starts from the first product so that it has the correct exponent
+                let mut sum = a[0] * b[SeriesApproximation::bivariate_index(i, j)];
+
+                for j1 in 0..=j {
+                    for i1 in 0..=i {
+                        if i1 + j1 > 0 {
+                            sum += a[SeriesApproximation::bivariate_index(i1, j1)] * b[SeriesApproximation::bivariate_index(i - i1, j - j1)];
+                        }
+                    }
+                }
+
+                sum.reduce();
+                output[SeriesApproximation::bivariate_index(i, j)] = sum;
+            }
+        }
+
+        output
+    }
+
+    // For power p: delta_n+1 = sum of C(p, m) * Z^(p - m) * delta^m, expanded with Horner's rule in delta
+    fn next_coefficients_power(&self, previous_coefficients: &[ComplexExtended], next_coefficients: &mut [ComplexExtended]) {
+        let fractal_power = self.fractal_type.power();
+
+        let mut series = previous_coefficients.to_vec();
+        series[0] = ComplexExtended::new2(0.0, 0.0, 0);
+
+        let mut sum = vec![ComplexExtended::new2(0.0, 0.0, 0); self.order + 1];
+        sum[0] = ComplexExtended::new2(1.0, 0.0, 0);
+
+        let mut reference_power = ComplexExtended::new2(1.0, 0.0, 0);
+
+        for m in (1..fractal_power).rev() {
+            reference_power *= previous_coefficients[0];
+            reference_power.reduce();
+
+            sum = SeriesApproximation::multiply_series(&sum, &series, self.order);
+            sum[0] += reference_power * self.pascal[m];
+            sum[0].reduce();
+        }
+
+        let product = SeriesApproximation::multiply_series(&sum, &series, self.order);
+
+        next_coefficients[1..].copy_from_slice(&product[1..]);
+    }
+
+    // While the delta stays in the quadrant of the reference, the abs variations are 2 * Z * delta + delta^2
+    // with the signs of the real and imaginary parts fixed by the reference
+    fn next_coefficients_bivariate(&self, previous_coefficients: &[ComplexExtended], next_coefficients: &mut [ComplexExtended]) {
+        let mut series = previous_coefficients.to_vec();
+        series[0] = ComplexExtended::new2(0.0, 0.0, 0);
+
+        let mut sum = series.clone();
+        sum[0] = previous_coefficients[0] * 2.0;
+
+        let product = SeriesApproximation::multiply_bivariate(&sum, &series, self.order);
+
+        let reference_z = previous_coefficients[0].mantissa;
+
+        let sign_re = reference_z.re.signum();
+        let sign_im = reference_z.im.signum();
+        let sign_square_re = (reference_z.re * reference_z.re - reference_z.im * reference_z.im).signum();
+
+        let (real_sign, imag_sign) = match self.fractal_type {
+            FractalType::Tricorn(_) => (1.0, -1.0),
+            FractalType::Celtic(_) => (sign_square_re, 1.0),
+            FractalType::Buffalo(_) => (sign_square_re, -sign_re * sign_im),
+            FractalType::PerpendicularBurningShip(_) => (1.0, -sign_im),
+            _ => (1.0, sign_re * sign_im)
+        };
+
+        for k in 1..next_coefficients.len() {
+            next_coefficients[k] = ComplexExtended::new2(real_sign * product[k].mantissa.re, imag_sign * product[k].mantissa.im, product[k].exponent);
+        }
+    }
+
+    // Steps a probe with the perturbation formula of the fractal type
+    #[inline]
+    fn step_probe(&self, probe: &mut ComplexExtended, probe_jacobian: &mut [ComplexExtended; 2], reference_z: ComplexExtended, probe_start: ComplexExtended, julia: bool) {
+        match self.fractal_type {
+            FractalType::Mandelbrot(_) => Perturbation::perturb_function_extended::<0, 0>(probe, probe_jacobian, reference_z, probe_start, &self.pascal, self.fractal_type.power(), julia),
+            FractalType::BurningShip(_) => Perturbation::perturb_function_extended::<0, 1>(probe, probe_jacobian, reference_z, probe_start, &self.pascal, 2, julia),
+            FractalType::Tricorn(_) => Perturbation::perturb_function_extended::<0, 2>(probe, probe_jacobian, reference_z, probe_start, &self.pascal, 2, julia),
+            FractalType::Celtic(_) => Perturbation::perturb_function_extended::<0, 3>(probe, probe_jacobian, reference_z, probe_start, &self.pascal, 2, julia),
+            FractalType::Buffalo(_) => Perturbation::perturb_function_extended::<0, 4>(probe, probe_jacobian, reference_z, probe_start, &self.pascal, 2, julia),
+            FractalType::PerpendicularBurningShip(_) => Perturbation::perturb_function_extended::<0, 5>(probe, probe_jacobian, reference_z, probe_start, &self.pascal, 2, julia)
+        }
+    }
+
+    // Powers of the real and imaginary parts of the delta, used for evaluating the bivariate series
+    fn bivariate_powers(&self, point_delta: ComplexExtended) -> (Vec<FloatExtended>, Vec<FloatExtended>) {
+        let mut real_powers = vec![FloatExtended::new(1.0, 0); self.order + 1];
+        let mut imag_powers = vec![FloatExtended::new(1.0, 0); self.order + 1];
+
+        for k in 1..=self.order {
+            real_powers[k] = real_powers[k - 1] * point_delta.real();
+            imag_powers[k] = imag_powers[k - 1] * point_delta.imag();
+        }
+
+        (real_powers, imag_powers)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn calculate_probes(&mut self, image_width: usize, image_height: usize, cos_rotate: f64, sin_rotate: f64, delta_top_left_mantissa: ComplexFixed<f64>, delta_top_left_exponent: i32, delta_pixel: f64) {
         // Delete the previous probes and calculate new ones
         self.probe_start = Vec::new();
@@ -175,29 +361,28 @@ impl SeriesApproximation {
             }
         };
 
+        let fractal_power = self.fractal_type.power();
+
         loop {
             valid_iterations.par_iter_mut().enumerate()
                 .for_each(|(i, probe_iteration_level)| {
-                    match selected_probes {
-                        Some(selected) => {
-                            if !selected.contains(&i) {
-                                return;
-                            }
-                        },
-                        None => {}
-                    };
+                    if selected_probes.is_some_and(|selected| !selected.contains(&i)) {
+                        return;
+                    }
 
                     // check if the probe has already found its max skip
                     if *probe_iteration_level == current_probe_check_value {
                         let mut probe = self.evaluate(self.probe_start[i], *probe_iteration_level);
 
+                        // Only used for the distance estimation, which is not needed for the probes
+                        let mut probe_jacobian = [ComplexExtended::new2(1.0, 0.0, 0), ComplexExtended::new2(0.0, 1.0, 0)];
+
                         while *probe_iteration_level < self.maximum_iteration {
                             // step the probe points using perturbation
-                            probe = probe * (center_reference.reference_data_extended[*probe_iteration_level] * 2.0 + probe);
-                            probe += self.probe_start[i];
+                            self.step_probe(&mut probe, &mut probe_jacobian, center_reference.reference_extended(*probe_iteration_level), self.probe_start[i], center_reference.julia);
 
-                            // This is not done on every iteration
-                            if *probe_iteration_level % 250 == 0 {
+                            // This is not done on every iteration, unless the higher powers could overflow
+                            if *probe_iteration_level % 250 == 0 || fractal_power > 2 {
                                 probe.reduce();
                             }
 
@@ -275,6 +460,7 @@ impl SeriesApproximation {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn check_approximation(&mut self, 
         delta_top_left_mantissa: ComplexFixed<f64>, 
         delta_top_left_exponent: i32, 
@@ -359,7 +545,7 @@ impl SeriesApproximation {
                 print!("{:>8},", element);
             }
 
-            print!("\x08]\n");
+            println!("\x08]");
         }
     }
 
@@ -374,7 +560,7 @@ impl SeriesApproximation {
                 print!("{:>8},", element);
             }
 
-            print!("\x08]\n");
+            println!("\x08]");
         }
     }
 
@@ -425,6 +611,22 @@ impl SeriesApproximation {
         // 101 -> 100 / 100 = 1, 1 -> 0 / 100 = 0, 201 -> 200 / 100 = 2
         let new_coefficients = &self.coefficients[(iteration - 1) / self.data_storage_interval];
 
+        if !self.fractal_type.holomorphic() {
+            let (real_powers, imag_powers) = self.bivariate_powers(point_delta);
+
+            // Starting from the linear terms keeps the exponent of the approximation at the scale of the delta
+            let mut approximation = new_coefficients[1] * real_powers[1] + new_coefficients[2] * imag_powers[1];
+
+            for degree in 2..=self.order {
+                for j in 0..=degree {
+                    approximation += new_coefficients[SeriesApproximation::bivariate_index(degree - j, j)] * (real_powers[degree - j] * imag_powers[j]);
+                }
+            }
+
+            approximation.reduce();
+            return approximation;
+        }
+
         // Horner's rule
         let mut approximation = new_coefficients[self.order];
 
@@ -446,6 +648,11 @@ impl SeriesApproximation {
         // 101 -> 100 / 100 = 1, 1 -> 0 / 100 = 0, 201 -> 200 / 100 = 2
         let new_coefficients = &self.coefficients[(iteration - 1) / self.data_storage_interval];
 
+        // For the bivariate series this is the derivative with respect to the real part of the delta
+        if !self.fractal_type.holomorphic() {
+            return self.evaluate_bivariate_derivatives(point_delta, new_coefficients)[0];
+        }
+
         // Horner's rule
         let mut approximation = new_coefficients[self.order];
         approximation *= self.order as f64;
@@ -458,4 +665,51 @@ impl SeriesApproximation {
         approximation.reduce();
         approximation
     }
-}
\ No newline at end of file
+
+    // The jacobian in the form used by the perturbation, [dx/du + i * dx/dv, dy/du + i * dy/dv]
+    pub fn evaluate_jacobian(&self, point_delta: ComplexExtended, iteration: usize) -> [ComplexExtended; 2] {
+        match self.fractal_type {
+            _ if !self.fractal_type.holomorphic() && iteration != 1 => {
+                let new_coefficients = &self.coefficients[(iteration - 1) / self.data_storage_interval];
+                let [derivative_real, derivative_imag] = self.evaluate_bivariate_derivatives(point_delta, new_coefficients);
+
+                let mut jacobian = [
+                    ComplexExtended::from_parts(derivative_real.real(), derivative_imag.real()),
+                    ComplexExtended::from_parts(derivative_real.imag(), derivative_imag.imag())
+                ];
+
+                jacobian[0].reduce();
+                jacobian
+            },
+            _ => [self.evaluate_derivative(point_delta, iteration), ComplexExtended::new2(0.0, 1.0, 0)]
+        }
+    }
+
+    // Derivatives of the bivariate series with respect to the real and imaginary parts of the delta
+    fn evaluate_bivariate_derivatives(&self, point_delta: ComplexExtended, new_coefficients: &[ComplexExtended]) -> [ComplexExtended; 2] {
+        let (real_powers, imag_powers) = self.bivariate_powers(point_delta);
+
+        let mut derivative_real = new_coefficients[1];
+        let mut derivative_imag = new_coefficients[2];
+
+        for degree in 2..=self.order {
+            for j in 0..=degree {
+                let i = degree - j;
+                let coefficient = new_coefficients[SeriesApproximation::bivariate_index(i, j)];
+
+                if i > 0 {
+                    derivative_real += coefficient * (real_powers[i - 1] * imag_powers[j]) * i as f64;
+                }
+
+                if j > 0 {
+                    derivative_imag += coefficient * (real_powers[i] * imag_powers[j - 1]) * j as f64;
+                }
+            }
+        }
+
+        derivative_real.reduce();
+        derivative_imag.reduce();
+
+        [derivative_real, derivative_imag]
+    }
+}
@@ -1,27 +1,66 @@
-use crate::util::{ComplexArbitrary, ComplexExtended, ComplexFixed, FloatExtended, FractalType, PixelData, ProgressCounters, data_export::*, extended_to_string_long, extended_to_string_short, generate_default_palette, generate_pascal_coefficients, get_approximation_terms, get_data_coloring_type_from_settings, get_delta_top_left, get_fractal_type_from_settings, string_to_extended};
-use crate::math::{SeriesApproximation, Perturbation, Reference, BoxPeriod};
-
-use std::{sync::{atomic::AtomicBool}, time::{Duration, Instant}};
-use std::io::Write;
+use crate::Error;
+use crate::util::{AverageSum, ComplexArbitrary, ComplexExtended, ComplexFixed, FloatExtended, FractalType, FrameStatistics, KfrLocation, MinibrotLocation, MisiurewiczLocation, MisiurewiczPoint, PixelData, ProgressCounters, ProgressObserver, RenderHandle, RenderStage, StageMonitor, TerminalProgress, data_export::*, extended_to_string_long, extended_to_string_short, generate_pascal_coefficients, get_approximation_terms, get_delta_top_left, string_to_extended, to_extended, to_fixed};
+use crate::settings::{ReferenceMode, RenderSettings, SkipMethod};
+use crate::math::{SeriesApproximation, BilinearApproximation, Perturbation, Reference, ReferenceFile, BoxPeriod, MisiurewiczPeriod, get_nucleus, get_nucleus_position, get_misiurewicz, get_misiurewicz_position};
+
+use std::time::{Duration, Instant};
+use std::fs;
+use std::path::Path;
 use std::cmp::{min, max};
 
 // use rand::seq::SliceRandom;
-use rand_distr::Distribution;
+use rand_distr::{Distribution, Normal};
 
-use colorgrad::{Color, CustomGradient, Interpolation, BlendMode};
 
 use rayon::prelude::*;
-use config::Config;
 
-use std::thread;
-use std::sync::{Arc, mpsc};
+use std::sync::Arc;
 use std::sync::atomic::{Ordering, AtomicUsize};
 use std::collections::HashMap;
 
 use parking_lot::Mutex;
 
-const FRACTAL_TYPE: usize = 0;
-const FRACTAL_POWER: usize = 2;
+// Number of reference iterations between checks of the checkpoint interval
+const CHECKPOINT_WINDOW: usize = 10000;
+
+// Number of reference iterations between checks for the period of the nucleus
+const PERIOD_WINDOW: usize = 1000;
+
+// Iterations per period that are needed for the boundary of a minibrot to be resolved
+const MINIBROT_ITERATIONS_PER_PERIOD: usize = 64;
+
+// The atom size fits the minibrot to the image height, so the zoom is reduced to show its surroundings
+const MINIBROT_ZOOM_MARGIN: f64 = 2.0;
+
+// Misiurewicz points are searched for in the start of the orbit, as the search time grows with the square of the iterations
+const MISIUREWICZ_SEARCH_ITERATIONS: usize = 5000;
+
+// Number of preperiod and period candidates that are checked with Newton's method
+const MISIUREWICZ_CANDIDATES: usize = 16;
+
+// Number of nested references a bin of glitched pixels gets, after which the remaining pixels are left glitched
+const MAXIMUM_GLITCH_DEPTH: usize = 64;
+
+// Selects the monomorphised FRACTAL_TYPE and FRACTAL_POWER for the fractal type read from the settings
+// The powers matched here need to cover everything up to MAXIMUM_FRACTAL_POWER
+macro_rules! match_fractal_type {
+    ($fractal_type:expr, |$fractal:ident, $power:ident| $body:expr) => {
+        match $fractal_type {
+            FractalType::BurningShip(_) => { const $fractal: usize = 1; const $power: usize = 2; $body },
+            FractalType::Tricorn(_) => { const $fractal: usize = 2; const $power: usize = 2; $body },
+            FractalType::Celtic(_) => { const $fractal: usize = 3; const $power: usize = 2; $body },
+            FractalType::Buffalo(_) => { const $fractal: usize = 4; const $power: usize = 2; $body },
+            FractalType::PerpendicularBurningShip(_) => { const $fractal: usize = 5; const $power: usize = 2; $body },
+            FractalType::Mandelbrot(3) => { const $fractal: usize = 0; const $power: usize = 3; $body },
+            FractalType::Mandelbrot(4) => { const $fractal: usize = 0; const $power: usize = 4; $body },
+            FractalType::Mandelbrot(5) => { const $fractal: usize = 0; const $power: usize = 5; $body },
+            FractalType::Mandelbrot(6) => { const $fractal: usize = 0; const $power: usize = 6; $body },
+            FractalType::Mandelbrot(7) => { const $fractal: usize = 0; const $power: usize = 7; $body },
+            FractalType::Mandelbrot(8) => { const $fractal: usize = 0; const $power: usize = 8; $body },
+            FractalType::Mandelbrot(_) => { const $fractal: usize = 0; const $power: usize = 2; $body },
+        }
+    };
+}
 
 pub struct FractalRenderer {
     pub image_width: usize,
@@ -29,6 +68,8 @@ pub struct FractalRenderer {
     pub total_pixels: usize,
     pub rotate: f64,
     pub zoom: FloatExtended,
+    pub center_real: String,
+    pub center_imag: String,
     pub auto_adjust_iterations: bool,
     pub maximum_iteration: usize,
     pub glitch_percentage: f64,
@@ -37,138 +78,162 @@ pub struct FractalRenderer {
     frame_offset: usize,
     pub zoom_scale_factor: f64,
     pub center_reference: Reference,
+    // Taken once the center reference has been loaded from or saved to the file
+    reference_file: Option<ReferenceFile>,
+    // Seconds between checkpoints of the center reference, which is disabled with 0
+    pub reference_checkpoint_interval: u64,
+    pub reference_mode: ReferenceMode,
+    // Position of the image centre relative to the center reference, which is moved when it is placed at a nucleus
+    pub reference_offset: ComplexExtended,
+    nucleus_search_pending: bool,
     pub series_approximation: SeriesApproximation,
+    pub bilinear_approximation: BilinearApproximation,
     pub period_finding: BoxPeriod,
     render_indices: Vec<usize>,
     pub remove_centre: bool,
     pub data_type: DataType,
+    // Finds the attracting cycles of interior pixels, which is needed for the interior distance
+    pub interior_detection: bool,
     pub jitter: bool,
-    pub jitter_factor: f64,
-    show_output: bool,
+    // Offset of each pixel in pixel widths, with the standard deviation given by the jitter factor
+    jitter_distribution: Normal<f64>,
+    // Receives the progress of the render, which is None when show_output is false
+    pub observer: Option<Arc<dyn ProgressObserver>>,
+    pub export_kfr: bool,
+    pub output_directory: String,
     pub progress: ProgressCounters,
     pub render_time: u128,
     pub fractal_type: FractalType,
     pub root_zoom_factor: f64,
-    pub pascal: Vec<f64>
+    pub pascal: Vec<f64>,
+    cancelled_frame: Option<CancelledFrame>
 }
 
-impl FractalRenderer {
-    pub fn new(settings: Config) -> Self {
-        let image_width = settings.get_int("image_width").unwrap_or(1000) as usize;
-        let image_height = settings.get_int("image_height").unwrap_or(1000) as usize;
+// The work that was kept from a cancelled frame, so that it can be resumed from the stage it was stopped in
+struct CancelledFrame {
+    frame_index: usize,
+    stage: RenderStage,
+    pixel_data: Vec<PixelData>
+}
 
-        let rotate = settings.get_float("rotate").unwrap_or(0.0).to_radians();
-        let maximum_iteration = settings.get_int("iterations").unwrap_or(1000) as usize;
+impl FractalRenderer {
+    // Panics if the settings are not valid, try_new returns the error instead
+    pub fn new(settings: RenderSettings) -> Self {
+        FractalRenderer::try_new(settings).unwrap_or_else(|error| panic!("{}", error))
+    }
 
-        let initial_zoom = settings.get_string("zoom").unwrap_or_else(|_| String::from("1E0")).to_ascii_uppercase();
-        let center_real = settings.get_string("real").unwrap_or_else(|_| String::from("-0.75"));
-        let center_imag = settings.get_string("imag").unwrap_or_else(|_| String::from("0.0"));
+    pub fn try_new(settings: RenderSettings) -> Result<Self, Error> {
+        settings.validate()?;
 
-        let approximation_order = settings.get_int("approximation_order").unwrap_or(0) as usize;
-        let glitch_percentage = settings.get_float("glitch_percentage").unwrap_or(0.001);
-        let remaining_frames = settings.get_int("frames").unwrap_or(1) as usize;
-        let frame_offset = settings.get_int("frame_offset").unwrap_or(0) as usize;
-        let zoom_scale_factor = settings.get_float("zoom_scale").unwrap_or(2.0);
-        let display_glitches = settings.get_bool("display_glitches").unwrap_or(false);
+        let image_width = settings.image.image_width;
+        let image_height = settings.image.image_height;
 
-        let auto_adjust_iterations = settings.get_bool("auto_adjust_iterations").unwrap_or(true);
-        let series_approximation_tiled = settings.get_bool("series_approximation_tiled").unwrap_or(true);
-        let series_approximation_enabled = settings.get_bool("series_approximation_enabled").unwrap_or(true);
+        let rotate = settings.location.rotate.to_radians();
+        let maximum_iteration = settings.location.iterations;
 
-        let probe_sampling = settings.get_int("probe_sampling").unwrap_or(3) as usize;
-        let remove_centre = settings.get_bool("remove_centre").unwrap_or(false);
+        let initial_zoom = settings.location.zoom.to_ascii_uppercase();
+        let center_real = settings.location.real.clone();
+        let center_imag = settings.location.imag.clone();
 
-        let palette_iteration_span = settings.get_float("palette_iteration_span").unwrap_or(100.0) as f32;
-        let palette_offset = settings.get_float("palette_offset").unwrap_or(0.0) as f32;
-        let palette_cyclic = settings.get_bool("palette_cyclic").unwrap_or(true);
+        let julia = settings.location.julia;
+        let julia_real = &settings.location.julia_real;
+        let julia_imag = &settings.location.julia_imag;
 
-        let distance_color = settings.get_bool("distance_color").unwrap_or(false);
+        let approximation_order = settings.approximation.approximation_order;
+        let glitch_percentage = settings.approximation.glitch_percentage;
+        let remaining_frames = settings.image.frames;
+        let frame_offset = settings.image.frame_offset;
+        let zoom_scale_factor = settings.image.zoom_scale;
+        let display_glitches = settings.colouring.display_glitches;
 
-        let lighting = settings.get_bool("lighting").unwrap_or(true);
+        let auto_adjust_iterations = settings.image.auto_adjust_iterations;
+        let series_approximation_tiled = settings.approximation.series_approximation_tiled;
 
-        let lighting_direction = settings.get_float("lighting_direction").unwrap_or(30.0) as f32;
-        let lighting_azimuth = settings.get_float("lighting_azimuth").unwrap_or(35.0) as f32;
-        let lighting_opacity = settings.get_float("lighting_opacity").unwrap_or(0.75) as f32;
-        let lighting_ambient = settings.get_float("lighting_ambient").unwrap_or(0.4) as f32;
-        let lighting_diffuse = settings.get_float("lighting_diffuse").unwrap_or(0.5) as f32;
-        let lighting_specular = settings.get_float("lighting_specular").unwrap_or(0.5) as f32;
-        let lighting_shininess = settings.get_int("lighting_shininess").unwrap_or(20) as i32;
+        // Either the series approximation skips the start of the orbit, or the bilinear approximation skips throughout
+        let (series_approximation_enabled, bilinear_approximation_enabled) = match settings.approximation.skip_method {
+            SkipMethod::Bilinear => (false, true),
+            SkipMethod::None => (false, false),
+            SkipMethod::Series => (settings.approximation.series_approximation_enabled, false)
+        };
 
-        let distance_transition = settings.get_float("distance_transition").unwrap_or(0.0) as f32;
+        let probe_sampling = settings.approximation.probe_sampling;
+        let remove_centre = settings.image.remove_centre;
 
-        let valid_iteration_probe_multiplier = settings.get_float("valid_iteration_probe_multiplier").unwrap_or(0.02) as f32;
-        let glitch_tolerance = settings.get_float("glitch_tolerance").unwrap_or(1.4e-6) as f64;
-        let data_storage_interval = settings.get_int("data_storage_interval").unwrap_or(10) as usize;
+        let palette_iteration_span = settings.colouring.palette_iteration_span;
+        let palette_offset = settings.colouring.palette_offset;
+        let palette_cyclic = settings.colouring.palette_cyclic;
 
-        let fractal_type = get_fractal_type_from_settings(&settings);
+        let distance_color = settings.colouring.distance_color;
 
-        let (coloring_type, data_type) = get_data_coloring_type_from_settings(&settings);
+        let lighting = &settings.lighting;
 
-        let stripe_scale = settings.get_float("stripe_scale").unwrap_or(1.0) as f32;
+        let distance_transition = settings.colouring.distance_transition;
 
-        let jitter = settings.get_bool("jitter").unwrap_or(false);
-        let jitter_factor = settings.get_float("jitter_factor").unwrap_or(0.2);
-        let show_output = settings.get_bool("show_output").unwrap_or(true);
-        
-        let export_type = match settings.get_string("export").unwrap_or_else(|_| String::from("COLOUR")).to_ascii_uppercase().as_ref() {
-            "GUI" => ExportType::Gui,
-            "RAW" | "EXR" => ExportType::Raw,
-            "BOTH" => ExportType::Both,
-            _ => ExportType::Color
-        };
+        let valid_iteration_probe_multiplier = settings.approximation.valid_iteration_probe_multiplier;
+        let glitch_tolerance = settings.approximation.glitch_tolerance;
+        let data_storage_interval = settings.approximation.data_storage_interval;
 
-        let (palette_buffer, palette_interpolated_buffer) = if let Ok(colour_values) = settings.get_array("palette") {
-            let mut colors = colour_values.chunks_exact(3).map(|value| {
-                Color::from_rgba8(value[0].clone().into_int().unwrap() as u8, 
-                    value[1].clone().into_int().unwrap() as u8, 
-                    value[2].clone().into_int().unwrap() as u8,
-                    255
-                )
-            }).collect::<Vec<Color>>();
-
-            if colors[0] != *colors.last().unwrap() {
-                colors.push(colors[0].clone());
-            };
+        let fractal_type = settings.fractal_type();
 
-            let mut number_colors = colors.len();
+        let coloring_type = settings.colouring.coloring_type;
+        let data_type = coloring_type.data_type();
 
-            if palette_cyclic {
-                number_colors -= 1;
-            }
+        let stripe_scale = settings.colouring.stripe_scale;
 
-            let palette_generator = CustomGradient::new()
-                .colors(&colors[0..number_colors])
-                .interpolation(Interpolation::CatmullRom)
-                .mode(BlendMode::Oklab)
-                .build().unwrap();
-
-            (colors, palette_generator.colors(number_colors * 64))
+        let jitter = settings.image.jitter;
+        let jitter_distribution = FractalRenderer::jitter_distribution(settings.image.jitter_factor)?;
+        let observer = if settings.image.show_output {
+            Some(Arc::new(TerminalProgress) as Arc<dyn ProgressObserver>)
         } else {
-            generate_default_palette()
+            None
         };
+        let export_kfr = settings.export.export_kfr;
+        let output_directory = settings.export.output_directory.clone();
+        
+        let export_type = settings.export.export;
+
+        let (palette_buffer, palette_interpolated_buffer) = settings.palette_buffers()?;
 
-        let mut zoom = string_to_extended(&initial_zoom);
+        let mut zoom = string_to_extended(&initial_zoom)?;
         let delta_pixel =  (-2.0 * (4.0 / image_height as f64 - 2.0) / zoom) / image_height as f64;
         let radius = delta_pixel * image_width as f64;
         let precision = max(64, -radius.exponent + 64);
 
         let center_location = ComplexArbitrary::with_val(
             precision as u32,
-            ComplexArbitrary::parse("(".to_owned() + &center_real + "," + &center_imag + ")").expect("provided location not valid"));
+            ComplexArbitrary::parse("(".to_owned() + &center_real + "," + &center_imag + ")")
+                .map_err(|_| Error::InvalidLocation(format!("({}, {})", center_real, center_imag)))?);
 
         let zero = ComplexArbitrary::with_val(
             center_location.prec().0 as u32,
-            ComplexArbitrary::parse("(0.0,0.0)").expect("provided location not valid"));
+            (0.0, 0.0));
+
+        // For Julia sets the location is the starting z and the seed is used for c
+        let (reference_z, reference_c) = if julia {
+            let julia_seed = ComplexArbitrary::with_val(
+                center_location.prec().0 as u32,
+                ComplexArbitrary::parse("(".to_owned() + julia_real + "," + julia_imag + ")")
+                    .map_err(|_| Error::InvalidLocation(format!("julia seed ({}, {})", julia_real, julia_imag)))?);
+
+            (center_location, julia_seed)
+        } else {
+            (zero, center_location)
+        };
 
         let auto_approximation = get_approximation_terms(approximation_order, image_width, image_height);
 
-        let reference = Reference::new(zero, 
-            center_location, 
+        let mut reference = Reference::new(reference_z, 
+            reference_c, 
             0, 
             maximum_iteration, 
             data_storage_interval,
             glitch_tolerance,
-            zoom);
+            zoom,
+            julia);
+
+        reference.compact = settings.approximation.compact_reference;
+
+        let reference_file = FractalRenderer::reference_file(&settings.approximation.reference_file, &reference, fractal_type);
 
         let series_approximation = SeriesApproximation::new_central(auto_approximation, 
             maximum_iteration, 
@@ -177,7 +242,8 @@ impl FractalRenderer {
             series_approximation_tiled,
             series_approximation_enabled,
             valid_iteration_probe_multiplier,
-            data_storage_interval);
+            data_storage_interval,
+            fractal_type);
 
         let temporary_delta = ComplexExtended::new2(0.0, 0.0, 0);
 
@@ -204,21 +270,27 @@ impl FractalRenderer {
                     distance_transition, 
                     stripe_scale,
                     distance_color,
-                    lighting,
+                    lighting.lighting,
                     coloring_type, 
                     data_type, 
                     fractal_type, 
                     export_type)
         ));
 
-        data_export.lock().change_lighting(lighting_direction, lighting_azimuth, lighting_opacity, lighting_ambient, lighting_diffuse, lighting_specular, lighting_shininess);
+        data_export.lock().interior_coloring = settings.colouring.interior_coloring;
+        data_export.lock().interior_palette_buffer = settings.interior_palette_buffer()?;
+
+        data_export.lock().lighting_blend = lighting.lighting_blend;
+        data_export.lock().change_lighting(lighting.lighting_direction, lighting.lighting_azimuth, lighting.lighting_opacity, lighting.lighting_ambient, lighting.lighting_diffuse, lighting.lighting_specular, lighting.lighting_shininess);
 
-        FractalRenderer {
+        Ok(FractalRenderer {
             image_width,
             image_height,
             total_pixels: render_indices.len(),
             rotate,
             zoom,
+            center_real,
+            center_imag,
             auto_adjust_iterations,
             maximum_iteration,
             glitch_percentage,
@@ -227,80 +299,283 @@ impl FractalRenderer {
             frame_offset,
             zoom_scale_factor,
             center_reference: reference,
+            reference_file,
+            reference_checkpoint_interval: settings.approximation.reference_checkpoint_interval,
+            reference_mode: settings.approximation.reference_mode,
+            reference_offset: ComplexExtended::new2(0.0, 0.0, 0),
+            nucleus_search_pending: settings.approximation.reference_mode == ReferenceMode::Nucleus,
             series_approximation,
+            bilinear_approximation: BilinearApproximation::new(bilinear_approximation_enabled),
             period_finding,
             render_indices,
             remove_centre,
             data_type,
+            interior_detection: settings.interior_detection(),
             jitter,
-            jitter_factor,
-            show_output,
+            jitter_distribution,
+            observer,
+            export_kfr,
+            output_directory,
             progress: ProgressCounters::new(maximum_iteration),
             render_time: 0,
             fractal_type,
             root_zoom_factor: 0.0,
-            pascal: generate_pascal_coefficients(FRACTAL_POWER + 1)
+            pascal: generate_pascal_coefficients(fractal_type.power() + 1),
+            cancelled_frame: None
+        })
+    }
+
+    pub fn render_frame(&mut self, frame_index: usize, filename: String, handle: &RenderHandle) -> Result<(), Error> {
+        // A new frame replaces a cancelled one, although a partially calculated reference is still continued
+        self.cancelled_frame = None;
+
+        self.save_frame(frame_index, filename, handle)
+    }
+
+    // Continues the last cancelled frame, keeping the pixels and approximations that were already calculated
+    // The location and image should not be changed before resuming, as the remaining pixels are used as they are
+    pub fn resume_frame(&mut self, filename: String, handle: &RenderHandle) -> Result<(), Error> {
+        match self.cancelled_frame.as_ref().map(|cancelled_frame| cancelled_frame.frame_index) {
+            Some(frame_index) => self.save_frame(frame_index, filename, handle),
+            None => Ok(())
+        }
+    }
+
+    // Renders the frame without writing any files, returning None if the render was cancelled
+    pub fn render_frame_to_buffer(&mut self, frame_index: usize, handle: &RenderHandle) -> Option<FrameData> {
+        self.cancelled_frame = None;
+
+        self.buffer_frame(frame_index, handle)
+    }
+
+    pub fn resume_frame_to_buffer(&mut self, handle: &RenderHandle) -> Option<FrameData> {
+        let frame_index = self.cancelled_frame.as_ref()?.frame_index;
+
+        self.buffer_frame(frame_index, handle)
+    }
+
+    // The stage that a cancelled frame will be resumed from
+    pub fn cancelled_stage(&self) -> Option<RenderStage> {
+        self.cancelled_frame.as_ref().map(|cancelled_frame| cancelled_frame.stage)
+    }
+
+    fn save_frame(&mut self, frame_index: usize, filename: String, handle: &RenderHandle) -> Result<(), Error> {
+        let frame_time = Instant::now();
+
+        if !self.calculate_frame(frame_index, handle, frame_time) {
+            return Ok(());
+        }
+
+        self.data_export.lock().save(&filename, self.series_approximation.order, &extended_to_string_long(self.zoom))?;
+
+        if self.export_kfr && !matches!(self.data_export.lock().export_type, ExportType::Gui | ExportType::None) {
+            self.save_kfr(&filename)?;
+        }
+
+        self.finish_frame(frame_index, frame_time);
+
+        Ok(())
+    }
+
+    fn buffer_frame(&mut self, frame_index: usize, handle: &RenderHandle) -> Option<FrameData> {
+        let frame_time = Instant::now();
+
+        if !self.calculate_frame(frame_index, handle, frame_time) {
+            return None;
         }
+
+        self.finish_frame(frame_index, frame_time);
+
+        Some(self.data_export.lock().frame_data())
     }
 
-    pub fn render_frame(&mut self, frame_index: usize, filename: String, stop_flag: Arc<AtomicBool>) {
+    fn finish_frame(&mut self, frame_index: usize, frame_time: Instant) {
+        self.render_time = frame_time.elapsed().as_millis();
+
+        if let Some(observer) = &self.observer {
+            observer.frame_finished(frame_index, frame_time.elapsed());
+        }
+    }
+
+    // Progress is only monitored when there is an observer to report it to
+    // Errors that do not stop the render are only reported, so nothing is printed when show_output is false
+    fn warning(&self, error: &Error) {
+        if let Some(observer) = &self.observer {
+            observer.warning(&error.to_string());
+        }
+    }
+
+    fn start_stage(&self, stage: RenderStage, pixel_offset: usize, pixel_count: usize) -> Option<StageMonitor> {
+        self.observer.as_ref().map(|observer| {
+            observer.stage_started(stage);
+            self.progress.monitor(stage, observer.clone(), pixel_offset, pixel_count)
+        })
+    }
+
+    fn finish_stage(&self, stage: RenderStage, monitor: Option<StageMonitor>, stage_time: Instant) {
+        if let Some(monitor) = monitor {
+            monitor.finish();
+        }
+
+        if let Some(observer) = &self.observer {
+            let statistics = FrameStatistics {
+                skipped_iterations: self.series_approximation.min_valid_iteration,
+                approximation_order: self.series_approximation.order,
+                maximum_iteration: self.maximum_iteration,
+                reference_count: self.progress.reference_count.load(Ordering::SeqCst),
+                reference_memory: self.center_reference.memory_usage(),
+            };
+
+            observer.stage_finished(stage, stage_time.elapsed(), &statistics);
+        }
+    }
+
+    // Calculates and colours all of the pixels, returning false if the render was cancelled
+    fn calculate_frame(&mut self, frame_index: usize, handle: &RenderHandle, frame_time: Instant) -> bool {
         self.progress.reset();
         
-        if self.show_output {
-            print!(" {:<15}", extended_to_string_short(self.zoom));
-            std::io::stdout().flush().unwrap();
+        if let Some(observer) = &self.observer {
+            observer.frame_started(frame_index, &extended_to_string_short(self.zoom));
         };
 
-        let frame_time = Instant::now();
-        let approximation_time = Instant::now();
+        // A frame that was cancelled after the approximation continues with the pixels that are left
+        let (stage, mut pixel_data) = match self.cancelled_frame.take() {
+            Some(cancelled_frame) if cancelled_frame.stage != RenderStage::Approximation => (cancelled_frame.stage, cancelled_frame.pixel_data),
+            _ => match self.calculate_approximation(frame_index, handle, frame_time) {
+                Some(pixel_data) => (RenderStage::Iteration, pixel_data),
+                None => {
+                    self.cancelled_frame = Some(CancelledFrame { frame_index, stage: RenderStage::Approximation, pixel_data: Vec::new() });
+                    return false;
+                }
+            }
+        };
+
+        let delta_pixel = 4.0 / ((self.image_height - 1) as f64 * self.zoom.mantissa);
+        let delta_pixel_extended = FloatExtended::new(delta_pixel, -self.zoom.exponent);
 
-        let (tx, rx) = mpsc::channel();
+        let total_pixels = self.render_indices.len();
 
-        if self.show_output {
-            let thread_counter_1 = self.progress.reference.clone();
-            let thread_counter_2 = self.progress.series_approximation.clone();
-            let thread_counter_3 = self.progress.reference_maximum.clone();
-            let thread_counter_4 = self.progress.series_validation.clone();
-            print!("|               ");
+        if stage == RenderStage::Iteration {
+            let iteration_time = Instant::now();
 
-            thread::spawn(move || {
-                loop {
-                    match rx.try_recv() {
-                        Ok(_) => {
-                            break;
-                        },
-                        Err(_) => {
-                            // 40% weighting to first reference, 40% to SA calculation, 20% to SA checking
-                            let mut percentage_complete = 0.0;
+            // Pixels that were finished before the frame was cancelled are not iterated again
+            let (mut remaining_pixels, mut finished_pixels): (Vec<PixelData>, Vec<PixelData>) = pixel_data.into_iter()
+                .partition(|pixel| !pixel.iterated);
 
-                            // println!("{} {} {}", thread_counter_1.get(), thread_counter_2.get(), thread_counter_3.get());
+            finished_pixels.retain(|pixel| pixel.glitched);
 
-                            percentage_complete += 45.0 * thread_counter_1.load(Ordering::Relaxed) as f64 / thread_counter_3.load(Ordering::Relaxed) as f64;
-                            percentage_complete += 45.0 * thread_counter_2.load(Ordering::Relaxed) as f64 / thread_counter_3.load(Ordering::Relaxed) as f64;
-                            percentage_complete += 10.0 * thread_counter_4.load(Ordering::Relaxed) as f64 / 2.0;
+            self.progress.iteration.store(total_pixels - remaining_pixels.len() - finished_pixels.len(), Ordering::SeqCst);
 
-                            print!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:^14}", format!("{:.2}%", percentage_complete));
-                            std::io::stdout().flush().unwrap();
-                        }
-                    };
+            let monitor = self.start_stage(RenderStage::Iteration, 0, total_pixels);
 
-                    thread::sleep(Duration::from_millis(100));
-                };
+            // The lower resolution passes are only used when all of the pixels are remaining
+            let progressive = remaining_pixels.len() == total_pixels;
+
+            self.iterate_pixels(&mut remaining_pixels, delta_pixel_extended, handle, progressive);
+
+            finished_pixels.append(&mut remaining_pixels);
+            pixel_data = finished_pixels;
+
+            if self.stop_rendering(handle, frame_time) {
+                self.cancelled_frame = Some(CancelledFrame { frame_index, stage: RenderStage::Iteration, pixel_data });
+                return false;
+            };
+
+            self.finish_stage(RenderStage::Iteration, monitor, iteration_time);
+
+            // Remove all non-glitched points from the remaining points
+            pixel_data.retain(|packet| {
+                packet.glitched
             });
+        }
+        
+        let correction_time = Instant::now();
+
+        self.progress.iteration.store(total_pixels - pixel_data.len(), Ordering::SeqCst);
+        self.progress.glitched_maximum.fetch_add(pixel_data.len(), Ordering::SeqCst);
+
+        let monitor = self.start_stage(RenderStage::Correction, total_pixels - pixel_data.len(), pixel_data.len());
+
+        // Glitches are solved until the remaining glitched pixels are below the allowed percentage
+        let maximum_glitched_pixels = (0.01 * self.glitch_percentage * total_pixels as f64) as usize;
+
+        if pixel_data.len() > maximum_glitched_pixels {
+            let remaining_glitched_pixels = AtomicUsize::new(pixel_data.len());
+
+            self.resolve_glitches(&mut pixel_data, handle, delta_pixel_extended, None, &remaining_glitched_pixels, maximum_glitched_pixels, 0);
+        }
+
+        // The glitched pixels are kept unchanged, so all of them are corrected again when resuming
+        if self.stop_rendering(handle, frame_time) {
+            self.cancelled_frame = Some(CancelledFrame { frame_index, stage: RenderStage::Correction, pixel_data });
+            return false;
         };
 
+        self.finish_stage(RenderStage::Correction, monitor, correction_time);
+        
+        true
+    }
+
+    // Calculates the reference, approximations and starting pixels, returning None if the render was cancelled
+    fn calculate_approximation(&mut self, frame_index: usize, handle: &RenderHandle, frame_time: Instant) -> Option<Vec<PixelData>> {
+        let approximation_time = Instant::now();
+
+        let monitor = self.start_stage(RenderStage::Approximation, 0, 0);
+
         if frame_index == 0 {
             self.data_export.lock().maximum_iteration = self.maximum_iteration;
 
-            self.center_reference.run::<FRACTAL_TYPE, FRACTAL_POWER>(&self.progress.reference, &self.progress.reference_maximum, &stop_flag);
+            if self.nucleus_search_pending {
+                self.find_reference_nucleus(handle);
 
-            if self.stop_rendering(&stop_flag, frame_time) {
-                tx.send(()).unwrap();
-                return;
+                if self.stop_rendering(handle, frame_time) {
+                    return None;
+                };
+            }
+
+            let mut reference_loaded = false;
+
+            // The saved reference is only used if the reference has not been started
+            if let Some(reference_file) = self.reference_file.as_ref().filter(|_| self.center_reference.reference_data.is_empty()) {
+                match reference_file.load(&mut self.center_reference, &mut self.series_approximation) {
+                    Ok(loaded) => reference_loaded = loaded,
+                    Err(error) => self.warning(&error)
+                }
+            }
+
+            // A completed orbit is continued if the maximum iteration has been increased
+            self.center_reference.extend(self.maximum_iteration);
+
+            let reference_calculated = !reference_loaded || !self.center_reference.complete;
+
+            self.run_center_reference(handle);
+
+            if self.stop_rendering(handle, frame_time) {
+                return None;
             };
             
             self.series_approximation.maximum_iteration = self.center_reference.current_iteration;
-            self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &stop_flag);
+
+            // Loaded coefficients are kept if they were saved with the same order for the same orbit
+            let approximation_loaded = reference_loaded && !reference_calculated && self.series_approximation.generated_order == self.series_approximation.order;
+
+            if !approximation_loaded {
+                self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, handle);
+            }
+
+            if self.stop_rendering(handle, frame_time) {
+                return None;
+            };
+
+            // The file is kept for the next frame 0, where the orbit is extended if the maximum iteration was increased
+            if let Some(reference_file) = &self.reference_file {
+                // The file is only written again if something new was calculated
+                if reference_calculated || (self.series_approximation.enabled && !approximation_loaded) {
+                    if let Err(error) = reference_file.save(&self.center_reference, Some(&self.series_approximation)) {
+                        self.warning(&error);
+                    }
+                }
+            }
         } else {
             let mut export = self.data_export.lock();
 
@@ -320,20 +595,14 @@ impl FractalRenderer {
             // Check to see if the series approximation order has changed intraframe
             if self.series_approximation.enabled && self.series_approximation.order != self.series_approximation.generated_order {
                 self.series_approximation.min_valid_iteration = 1;
-                self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &stop_flag);
+                self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, handle);
             }
         }
 
-        self.data_type = match self.data_export.lock().coloring_type {
-            ColoringType::SmoothIteration | ColoringType::StepIteration => DataType::Iteration,
-            ColoringType::Stripe => DataType::Stripe,
-            ColoringType::DistanceStripe => DataType::DistanceStripe,
-            _ => DataType::Distance
-        };
+        self.data_type = self.data_export.lock().coloring_type.data_type();
 
-        if self.stop_rendering(&stop_flag, frame_time) {
-            tx.send(()).unwrap();
-            return;
+        if self.stop_rendering(handle, frame_time) {
+            return None;
         };
         
         let cos_rotate = self.rotate.cos();
@@ -344,7 +613,11 @@ impl FractalRenderer {
         let delta_pixel_cos = delta_pixel * cos_rotate;
         let delta_pixel_sin = delta_pixel * sin_rotate;
 
-        let delta_top_left = get_delta_top_left(delta_pixel, self.image_width, self.image_height, cos_rotate, sin_rotate);
+        let centre_delta_top_left = get_delta_top_left(delta_pixel, self.image_width, self.image_height, cos_rotate, sin_rotate);
+
+        // The pixel deltas are relative to the reference, which can be away from the image centre
+        let reference_offset = self.reference_offset_at_zoom();
+        let delta_top_left = centre_delta_top_left + reference_offset;
         let delta_pixel_extended = FloatExtended::new(delta_pixel, -self.zoom.exponent);
 
         let minimum_dimension = min(self.image_width, self.image_height);
@@ -369,22 +642,20 @@ impl FractalRenderer {
             &self.center_reference,
             &self.progress.series_validation);
 
+        // The validity radii depend on the largest pixel delta, so this is regenerated every frame
+        let mut maximum_delta = FloatExtended::new(centre_delta_top_left.norm() + reference_offset.norm(), -self.zoom.exponent);
+        maximum_delta.reduce();
+
+        self.bilinear_approximation.generate(&self.center_reference, maximum_delta, self.fractal_type);
+
         self.progress.min_series_approximation.store(self.series_approximation.min_valid_iteration, Ordering::SeqCst);
         self.progress.max_series_approximation.store(self.series_approximation.max_valid_iteration, Ordering::SeqCst);
 
-        tx.send(()).unwrap();
-
-        if self.stop_rendering(&stop_flag, frame_time) {
-            return;
+        if self.stop_rendering(handle, frame_time) {
+            return None;
         };
 
-        if self.show_output {
-            print!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:<15}", approximation_time.elapsed().as_millis());
-            print!("| {:<15}", self.series_approximation.min_valid_iteration);
-            print!("| {:<6}", self.series_approximation.order);
-            print!("| {:<15}", self.maximum_iteration);
-            std::io::stdout().flush().unwrap();
-        };
+        self.finish_stage(RenderStage::Approximation, monitor, approximation_time);
 
         if self.remove_centre != self.data_export.lock().centre_removed {
             self.render_indices = FractalRenderer::generate_render_indices(self.image_width, self.image_height, self.remove_centre, self.zoom_scale_factor, self.data_export.lock().export_type);
@@ -395,9 +666,66 @@ impl FractalRenderer {
         let sampling_resolution_width = (self.series_approximation.probe_sampling - 1) as f64 / self.image_width as f64;
         let sampling_resolution_height = (self.series_approximation.probe_sampling - 1) as f64 / self.image_height as f64;
 
-        let normal = rand_distr::Normal::new(0.0, self.jitter_factor).unwrap();
+        // The iterations skipped by the series approximation are close to the reference, so the atom domain starts from the
+        // smallest |Z| before the skip
+        let atom_domain_start = if self.data_type == DataType::AtomDomain || self.interior_detection {
+            let skipped = if self.series_approximation.enabled {
+                self.series_approximation.max_valid_iteration.max(1)
+            } else {
+                1
+            };
+
+            let mut minimum = (f64::INFINITY, 0);
+
+            (0..=skipped).map(|iteration| {
+                let start = minimum;
+
+                if iteration > 0 {
+                    let z_norm = self.center_reference.reference_extended(iteration).norm_square().to_float();
+
+                    if z_norm < minimum.0 {
+                        minimum = (z_norm, iteration);
+                    }
+                }
+
+                start
+            }).collect::<Vec<(f64, usize)>>()
+        } else {
+            Vec::new()
+        };
+
+        // The averages also start from the terms of the reference before the skip
+        let average_start = if self.data_type == DataType::TriangleInequality || self.data_type == DataType::Curvature {
+            let skipped = if self.series_approximation.enabled {
+                self.series_approximation.max_valid_iteration.max(1)
+            } else {
+                1
+            };
+
+            let c_norm = to_fixed(&self.center_reference.c).norm();
+            let power = self.fractal_type.power() as i32;
+            let mut average = AverageSum::new();
 
-        let mut pixel_data = (&self.render_indices).into_par_iter()
+            (0..=skipped).map(|iteration| {
+                let start = average;
+
+                if iteration > 0 {
+                    let z = self.center_reference.reference_extended(iteration).to_float();
+
+                    if self.data_type == DataType::TriangleInequality {
+                        average.add_triangle_inequality(z, c_norm, power);
+                    } else {
+                        average.add_curvature(z);
+                    }
+                }
+
+                start
+            }).collect::<Vec<AverageSum>>()
+        } else {
+            Vec::new()
+        };
+
+        let pixel_data = (&self.render_indices).into_par_iter()
             .map(|index| {
                 let mut i = (index % self.image_width) as f64;
                 let mut j = (index / self.image_width) as f64;
@@ -420,8 +748,8 @@ impl FractalRenderer {
                 if self.jitter {
                     let mut rng = rand::thread_rng();
 
-                    i += normal.sample(&mut rng);
-                    j += normal.sample(&mut rng);
+                    i += self.jitter_distribution.sample(&mut rng);
+                    j += self.jitter_distribution.sample(&mut rng);
                 }
 
                 let element = ComplexFixed::new(
@@ -431,6 +759,11 @@ impl FractalRenderer {
 
                 let point_delta = ComplexExtended::new(element, -self.zoom.exponent);
 
+                let (atom_domain_norm, atom_domain_period) = atom_domain_start.get(chosen_iteration)
+                    .or_else(|| atom_domain_start.last())
+                    .copied()
+                    .unwrap_or((f64::INFINITY, 0));
+
                 PixelData {
                     index: *index,
                     iteration: chosen_iteration,
@@ -441,147 +774,385 @@ impl FractalRenderer {
                     z_norm: 0.0,
                     stripe_storage: [ComplexFixed::new(0.0, 0.0); 4],
                     stripe_iteration: 0,
+                    atom_domain_norm,
+                    atom_domain_period,
+                    derivative_z: ComplexExtended::new2(1.0, 0.0, 0),
+                    interior_z: ComplexFixed::new(0.0, 0.0),
+                    interior_distance: FloatExtended::new(0.0, 0),
+                    interior_period: 0,
+                    average: average_start.get(chosen_iteration)
+                        .or_else(|| average_start.last())
+                        .copied()
+                        .unwrap_or_default(),
+                    glitched: false,
+                    iterated: false,
                 }
             }).collect::<Vec<PixelData>>();
 
-        if self.stop_rendering(&stop_flag, frame_time) {
-            return;
+        if self.stop_rendering(handle, frame_time) {
+            return None;
         };
-        
-        let iteration_time = Instant::now();
-
-        let total_pixels = self.render_indices.len() as f64;
-
-        let (tx, rx) = mpsc::channel();
 
-        if self.show_output {
-            let thread_counter = Arc::clone(&self.progress.iteration);
-            print!("|               ");
+        Some(pixel_data)
+    }
 
-            thread::spawn(move || {
-                loop {
-                    thread::sleep(Duration::from_millis(100));
-                    match rx.try_recv() {
-                        Ok(_) => {
-                            break;
-                        },
-                        Err(_) => {
-                            print!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:^14}", format!("{:.2}%", 100.0 * thread_counter.load(Ordering::SeqCst) as f64 / total_pixels));
-                            std::io::stdout().flush().unwrap();
-                        }
-                    };
-                };
-            });
+    // Iterates the pixels, starting with lower resolution passes when progressive so the GUI can show a preview
+    fn iterate_pixels(&self, pixel_data: &mut [PixelData], delta_pixel_extended: FloatExtended, handle: &RenderHandle, progressive: bool) {
+        let values: &[usize] = if progressive {
+            &[16, 8, 4, 2, 1]
+        } else {
+            &[1]
         };
-
-        let values = [16usize, 8, 4, 2, 1];
         let mut previous_value = 0;
-        let number_pixels = self.render_indices.len();
+        let number_pixels = pixel_data.len();
 
         for &value in values.iter() {
             let end_value = number_pixels / (value * value);
             let chunk_size = max((end_value - previous_value) / 512, 8);
 
-            match self.data_type {
-                DataType::Distance => {
-                    Perturbation::iterate::<1, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, true, &self.pascal);
-                },
-                DataType::Stripe => {
-                    Perturbation::iterate::<2, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, true, &self.pascal);
-                },
-                DataType::DistanceStripe => {
-                    Perturbation::iterate::<3, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, true, &self.pascal);
-                },
-                _ => {
-                    Perturbation::iterate::<0, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, true, &self.pascal);
+            match_fractal_type!(self.fractal_type, |FRACTAL_TYPE, FRACTAL_POWER| {
+                match self.data_type {
+                    DataType::Distance => {
+                        Perturbation::iterate::<1, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, &self.bilinear_approximation, true, self.interior_detection, &self.pascal);
+                    },
+                    DataType::Stripe => {
+                        Perturbation::iterate::<2, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, &self.bilinear_approximation, true, self.interior_detection, &self.pascal);
+                    },
+                    DataType::DistanceStripe => {
+                        Perturbation::iterate::<3, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, &self.bilinear_approximation, true, self.interior_detection, &self.pascal);
+                    },
+                    DataType::AtomDomain => {
+                        Perturbation::iterate::<4, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, &self.bilinear_approximation, true, self.interior_detection, &self.pascal);
+                    },
+                    DataType::TriangleInequality => {
+                        Perturbation::iterate::<5, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, &self.bilinear_approximation, true, self.interior_detection, &self.pascal);
+                    },
+                    DataType::Curvature => {
+                        Perturbation::iterate::<6, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, &self.bilinear_approximation, true, self.interior_detection, &self.pascal);
+                    },
+                    _ => {
+                        Perturbation::iterate::<0, FRACTAL_TYPE, FRACTAL_POWER>(&mut pixel_data[previous_value..end_value], &self.center_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, value, chunk_size, &self.series_approximation, &self.bilinear_approximation, true, self.interior_detection, &self.pascal);
+                    }
                 }
-            }
+            });
 
             previous_value = end_value;
         }
+    }
 
-        tx.send(()).unwrap();
+    // The orbit is calculated in windows when checkpointing, saving the reference file whenever the checkpoint interval has passed
+    fn run_center_reference(&mut self, handle: &RenderHandle) {
+        let reference_file = match &self.reference_file {
+            Some(reference_file) if self.reference_checkpoint_interval > 0 => reference_file,
+            _ => {
+                match_fractal_type!(self.fractal_type, |FRACTAL_TYPE, FRACTAL_POWER| {
+                    self.center_reference.run::<FRACTAL_TYPE, FRACTAL_POWER>(&self.progress.reference, &self.progress.reference_maximum, handle)
+                });
 
-        if self.stop_rendering(&stop_flag, frame_time) {
-            return;
+                return;
+            }
         };
 
-        if self.show_output {
-            print!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:<15}", iteration_time.elapsed().as_millis());
-            std::io::stdout().flush().unwrap();
+        let mut checkpoint_interval = Duration::from_secs(self.reference_checkpoint_interval);
+        let mut checkpoint_time = Instant::now();
+
+        while !self.center_reference.complete && !handle.is_cancelled() {
+            let window_end = self.center_reference.current_iteration + CHECKPOINT_WINDOW;
+
+            match_fractal_type!(self.fractal_type, |FRACTAL_TYPE, FRACTAL_POWER| {
+                self.center_reference.run_until::<FRACTAL_TYPE, FRACTAL_POWER>(window_end, &self.progress.reference, &self.progress.reference_maximum, handle)
+            });
+
+            // The completed reference is saved with the series approximation afterwards
+            if !self.center_reference.complete && checkpoint_time.elapsed() >= checkpoint_interval {
+                let save_time = Instant::now();
+
+                if let Err(error) = reference_file.save(&self.center_reference, None) {
+                    self.warning(&error);
+                }
+
+                // The whole orbit is written each time, so the checkpoints are spaced out to keep the saving time small
+                checkpoint_interval = checkpoint_interval.max(save_time.elapsed() * 10);
+                checkpoint_time = Instant::now();
+            }
+        }
+    }
+
+    // Moves the center reference to the nucleus of the lowest period atom in the image
+    // If there is no atom, or the nucleus is outside of the image, the center reference is kept
+    fn find_reference_nucleus(&mut self, handle: &RenderHandle) {
+        let corners = self.image_corners();
+
+        let (mut nucleus, _) = match FractalRenderer::find_nucleus(&mut self.center_reference, corners, self.zoom, &self.progress, handle) {
+            Some(nucleus) => nucleus,
+            None => {
+                // The search is continued when the frame is resumed
+                self.nucleus_search_pending = handle.is_cancelled();
+                return;
+            }
         };
-        
-        // let correction_time = Instant::now();
-
-        // // Remove all non-glitched points from the remaining points
-        // pixel_data.retain(|packet| {
-        //     packet.glitched
-        // });
-
-        // let glitched_pixels = pixel_data.len() as f64;
-        // self.progress.glitched_maximum.fetch_add(pixel_data.len(), Ordering::SeqCst);
-
-        // let complete_pixels = total_pixels - glitched_pixels;
-
-        // let (tx, rx) = mpsc::channel();
-
-        // if self.show_output {
-        //     let thread_counter = Arc::clone(&self.progress.iteration);
-        //     print!("|               ");
-
-        //     thread::spawn(move || {
-        //         loop {
-        //             thread::sleep(Duration::from_millis(100));
-        //             match rx.try_recv() {
-        //                 Ok(_) => {
-        //                     break;
-        //                 },
-        //                 Err(_) => {
-        //                     print!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:^14}", format!("{:.2}%", 100.0 * (thread_counter.load(Ordering::Relaxed) as f64 - complete_pixels) / glitched_pixels));
-        //                     std::io::stdout().flush().unwrap();
-        //                 }
-        //             };
-        //         };
-        //     });
-        // };
-
-        // // Goes through all glitches and solved them - no need for glitch percentage at this time
-        // if pixel_data.len() > 0 {
-        //     self.resolve_glitches(&mut pixel_data, &stop_flag, frame_time, delta_pixel_extended, None);
-        // }
-
-        // tx.send(()).unwrap();
-
-        // if self.show_output {
-        //     print!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:<15}", correction_time.elapsed().as_millis());
-        //     print!("| {:<6}", self.progress.reference_count.load(Ordering::SeqCst));
-        //     std::io::stdout().flush().unwrap();
-        // };
-        
-        self.data_export.lock().save(&filename, self.series_approximation.order, &extended_to_string_long(self.zoom));
 
-        self.render_time = frame_time.elapsed().as_millis();
+        self.nucleus_search_pending = false;
+
+        // The nucleus is found at a higher precision than is needed for the image
+        nucleus.set_prec(self.center_reference.c.prec());
+
+        let mut reference_offset = to_extended(&(self.center_reference.c.clone() - &nucleus));
+        reference_offset.reduce();
+
+        let zero = ComplexArbitrary::with_val(nucleus.prec(), (0.0, 0.0));
+
+        let mut reference = Reference::new(zero, 
+            nucleus, 
+            0, 
+            self.maximum_iteration, 
+            self.center_reference.data_storage_interval, 
+            self.center_reference.glitch_tolerance, 
+            self.center_reference.zoom, 
+            false);
+
+        reference.compact = self.center_reference.compact;
+
+        // The reference file is for the orbit of the nucleus
+        if let Some(reference_file) = &self.reference_file {
+            self.reference_file = Some(ReferenceFile::new(&reference_file.path, &reference, self.fractal_type));
+        }
+
+        self.center_reference = reference;
+        self.reference_offset = reference_offset;
+    }
+
+    // Finds the lowest period minibrot in the image, with the zoom and rotation that fit it to the image
+    // Returns None if there is no minibrot in the image, or if the search was cancelled
+    pub fn find_minibrot(&self, handle: &RenderHandle) -> Result<Option<MinibrotLocation>, Error> {
+        let mut reference = self.search_reference("minibrots")?;
+
+        let (nucleus, period) = match FractalRenderer::find_nucleus(&mut reference, self.image_corners(), self.zoom, &self.progress, handle) {
+            Some(nucleus) => nucleus,
+            None => {
+                // The cancellation is cleared as when a frame is stopped, so that the handle can be used again
+                handle.clear_cancelled();
+                return Ok(None);
+            }
+        };
+
+        let (mut zoom, rotate) = get_nucleus_position(nucleus.clone(), period);
+        zoom.mantissa /= MINIBROT_ZOOM_MARGIN;
+
+        // The minibrot is deeper than the image, so it needs at least as many iterations
+        let iterations = self.maximum_iteration.max(MINIBROT_ITERATIONS_PER_PERIOD * period);
+
+        Ok(Some(MinibrotLocation {
+            nucleus,
+            period,
+            zoom,
+            iterations,
+            rotate: rotate.to_degrees(),
+        }))
+    }
+
+    // Finds the Misiurewicz point in the image with the lowest period and preperiod, with the zoom and rotation that fit its features to the image
+    // Returns None if there is no Misiurewicz point in the image, or if the search was cancelled
+    pub fn find_misiurewicz(&self, handle: &RenderHandle) -> Result<Option<MisiurewiczLocation>, Error> {
+        let mut reference = self.search_reference("Misiurewicz points")?;
+
+        let search_iterations = self.maximum_iteration.min(MISIUREWICZ_SEARCH_ITERATIONS);
+
+        reference.run_until::<0, 2>(search_iterations, &self.progress.reference, &self.progress.reference_maximum, handle);
+
+        if handle.is_cancelled() {
+            handle.clear_cancelled();
+            return Ok(None);
+        }
+
+        let corners = self.image_corners();
+        let mut misiurewicz_period = MisiurewiczPeriod::new(corners[0].norm());
+
+        // Each candidate is checked with Newton's method, as the single step from the image centre is only an estimate
+        for (preperiod, period) in misiurewicz_period.find_candidates(&reference, search_iterations).into_iter().take(MISIUREWICZ_CANDIDATES) {
+            if handle.should_stop() {
+                handle.clear_cancelled();
+                return Ok(None);
+            }
+
+            let point = match get_misiurewicz(reference.c.clone(), preperiod, period, handle) {
+                Some(point) => point,
+                None => continue
+            };
+
+            if !FractalRenderer::in_image(&reference.c, &point, corners[0], self.zoom) {
+                continue;
+            }
+
+            let (size, multiplier) = get_misiurewicz_position(point.clone(), preperiod, period);
+
+            // The features repeat towards the point, so the image is only zoomed in to the size of the first repetition
+            let mut zoom = FloatExtended::new(2.0, 0) / size.norm();
+            zoom.reduce();
+
+            if zoom < self.zoom {
+                zoom = self.zoom;
+            }
+
+            return Ok(Some(MisiurewiczLocation {
+                misiurewicz: MisiurewiczPoint {
+                    point,
+                    preperiod,
+                    period,
+                    size: size.norm(),
+                    multiplier,
+                },
+                zoom,
+                iterations: self.maximum_iteration,
+                rotate: size.mantissa.arg().to_degrees(),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    // A reference at the image centre for finding features in the image
+    fn search_reference(&self, features: &str) -> Result<Reference, Error> {
+        // The period and nucleus finding are only implemented for the power 2 mandelbrot
+        if self.fractal_type != FractalType::Mandelbrot(2) || self.center_reference.julia {
+            return Err(Error::InvalidSettings(format!("{} can only be found for the power 2 mandelbrot without julia", features)));
+        }
+
+        // The center reference may have been moved, so the search starts again from the image centre
+        let precision = self.center_reference.c.prec();
+        let center_location = ComplexArbitrary::with_val(
+            precision,
+            ComplexArbitrary::parse("(".to_owned() + &self.center_real + "," + &self.center_imag + ")")
+                .map_err(|_| Error::InvalidLocation(format!("({}, {})", self.center_real, self.center_imag)))?);
+
+        Ok(Reference::new(ComplexArbitrary::new(precision), 
+            center_location, 
+            0, 
+            self.maximum_iteration, 
+            self.center_reference.data_storage_interval, 
+            self.center_reference.glitch_tolerance, 
+            self.zoom, 
+            false))
+    }
+
+    // Checks that a point found from the image centre is inside of the image
+    fn in_image(centre: &ComplexArbitrary, point: &ComplexArbitrary, corner: ComplexExtended, zoom: FloatExtended) -> bool {
+        let mut image_offset = to_extended(&(centre.clone() - point));
+        image_offset.reduce();
+        image_offset.scale_to_exponent(-zoom.exponent);
+
+        let mut image_corner = corner;
+        image_corner.scale_to_exponent(-zoom.exponent);
+
+        image_offset.mantissa.norm() <= image_corner.mantissa.norm()
+    }
+
+    // Runs the reference at the image centre until the period of the lowest period atom in the image is found, and returns the nucleus and period of the atom
+    // Returns None if there is no atom, the nucleus is outside of the image or the search was cancelled
+    fn find_nucleus(reference: &mut Reference, corners: [ComplexExtended; 4], zoom: FloatExtended, progress: &ProgressCounters, handle: &RenderHandle) -> Option<(ComplexArbitrary, usize)> {
+        let mut box_period = BoxPeriod::new(ComplexExtended::new2(0.0, 0.0, 0), corners);
+
+        // The period is checked as the reference is calculated, so that only the start of the orbit is needed
+        while !reference.complete && box_period.period >= reference.current_iteration {
+            let window_end = reference.current_iteration + PERIOD_WINDOW;
+
+            reference.run_until::<0, 2>(window_end, &progress.reference, &progress.reference_maximum, handle);
+
+            if handle.is_cancelled() {
+                return None;
+            }
+
+            box_period.find_period(reference);
+        }
+
+        if box_period.period >= reference.current_iteration {
+            return None;
+        }
+
+        let nucleus = get_nucleus(reference.c.clone(), 
+            box_period.period, 
+            Arc::new(AtomicUsize::new(0)), 
+            Arc::new(AtomicUsize::new(0)), 
+            handle, 
+            Arc::new(Mutex::new(ComplexExtended::new2(0.0, 0.0, 0))))?;
 
-        if self.show_output {
-            println!("| {:<15}", frame_time.elapsed().as_millis());
-            std::io::stdout().flush().unwrap();
+        // Newton's method can converge to a nucleus outside of the image
+        if !FractalRenderer::in_image(&reference.c, &nucleus, corners[0], zoom) {
+            return None;
         }
+
+        Some((nucleus, box_period.period))
+    }
+
+    // Deltas of the corners of the image from the centre, going around the image
+    fn image_corners(&self) -> [ComplexExtended; 4] {
+        let cos_rotate = self.rotate.cos();
+        let sin_rotate = self.rotate.sin();
+
+        let delta_pixel = 4.0 / ((self.image_height - 1) as f64 * self.zoom.mantissa);
+        let delta_top_left = get_delta_top_left(delta_pixel, self.image_width, self.image_height, cos_rotate, sin_rotate);
+
+        let corner = |i: usize, j: usize| {
+            let (i, j) = (i as f64, j as f64);
+
+            let mut corner = ComplexExtended::new2(
+                i * delta_pixel * cos_rotate - j * delta_pixel * sin_rotate + delta_top_left.re,
+                i * delta_pixel * sin_rotate + j * delta_pixel * cos_rotate + delta_top_left.im,
+                -self.zoom.exponent);
+
+            corner.reduce();
+            corner
+        };
+
+        [corner(0, 0), corner(self.image_width - 1, 0), corner(self.image_width - 1, self.image_height - 1), corner(0, self.image_height - 1)]
+    }
+
+    fn reference_offset_at_zoom(&self) -> ComplexFixed<f64> {
+        let mut reference_offset = self.reference_offset;
+        reference_offset.scale_to_exponent(-self.zoom.exponent);
+        reference_offset.mantissa
+    }
+
+    fn reference_file(path: &str, reference: &Reference, fractal_type: FractalType) -> Option<ReferenceFile> {
+        if path.is_empty() {
+            None
+        } else {
+            Some(ReferenceFile::new(path, reference, fractal_type))
+        }
+    }
+
+    // Writes the location of the current frame so that it can be opened in Kalles Fraktaler
+    pub fn save_kfr(&self, filename: &str) -> Result<(), Error> {
+        let data_export = self.data_export.lock();
+
+        KfrLocation {
+            real: self.center_real.clone(),
+            imag: self.center_imag.clone(),
+            zoom: extended_to_string_long(self.zoom),
+            iterations: self.maximum_iteration,
+            rotate: self.rotate.to_degrees(),
+            fractal_type: self.fractal_type,
+            palette: &data_export.palette_buffer,
+            palette_offset: data_export.palette_offset,
+            palette_iteration_span: data_export.palette_iteration_span,
+        }.save(filename)
     }
 
     // Recursive glitch solving by glitch levels
     // Start with a central reference that has ALL data stored for each iteration past the min skip
-    pub fn resolve_glitches(&self, pixel_data: &mut [PixelData], stop_flag: &Arc<AtomicBool>, frame_time: Instant, delta_pixel_extended: FloatExtended, previous_reference: Option<Reference>) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn resolve_glitches(&self, pixel_data: &mut [PixelData], handle: &RenderHandle, delta_pixel_extended: FloatExtended, previous_reference: Option<Reference>, remaining_glitched_pixels: &AtomicUsize, maximum_glitched_pixels: usize, depth: usize) {
         let mut iteration_map: HashMap<usize, Vec<PixelData>> = HashMap::new();
 
-        // Sort into bins to process
+        // Sort into bins to process, pixels are binned by the reference iteration as rebasing means this can differ from the pixel iteration
         pixel_data.iter()
             .for_each(|pixel| {
-                match iteration_map.get_mut(&pixel.iteration) {
+                match iteration_map.get_mut(&pixel.reference_iteration) {
                     Some(pixels) => {
                         pixels.push(pixel.clone());
                     }
                     None => {
-                        iteration_map.insert(pixel.iteration, vec![pixel.clone()]);
+                        iteration_map.insert(pixel.reference_iteration, vec![pixel.clone()]);
                     }
                 }
             });
@@ -619,7 +1190,9 @@ impl FractalRenderer {
                 // We cap this to avoid running the reference too long when the central point has a large amount of iterations relative to the rest of the pixels
                 previous_reference.maximum_iteration = min(highest_iteration + 10000, previous_maximum_iteration);
 
-                previous_reference.run::<FRACTAL_TYPE, FRACTAL_POWER>(&Arc::new(AtomicUsize::new(0)), &Arc::new(AtomicUsize::new(0)), &stop_flag);
+                match_fractal_type!(self.fractal_type, |FRACTAL_TYPE, FRACTAL_POWER| {
+                    previous_reference.run::<FRACTAL_TYPE, FRACTAL_POWER>(&Arc::new(AtomicUsize::new(0)), &Arc::new(AtomicUsize::new(0)), handle)
+                });
 
                 // Reset the maximum iteration of the reference back to normal to make it seem the reference escaped early
                 previous_reference.maximum_iteration = previous_maximum_iteration;
@@ -628,70 +1201,94 @@ impl FractalRenderer {
             }
         };
 
-        if stop_flag.load(Ordering::SeqCst) {
+        if handle.is_cancelled() {
             return;
         };
 
         iteration_map.par_iter_mut()
             .for_each(|(iteration, pixel_data)| {
+                // Other bins may have already reduced the glitches to an acceptable level
+                if remaining_glitched_pixels.load(Ordering::SeqCst) <= maximum_glitched_pixels {
+                    return;
+                }
+
+                // A NaN z_norm is ordered last, so that it is never picked as the new reference
                 let glitch_reference_pixel = pixel_data.iter().min_by(|i, j| {
-                    i.z_norm.partial_cmp(&j.z_norm).unwrap()
+                    i.z_norm.total_cmp(&j.z_norm)
                 }).unwrap().clone();
 
                 let mut glitch_reference = previous_reference.get_glitch_resolving_reference(*iteration, glitch_reference_pixel.delta_reference, glitch_reference_pixel.delta_current);
-                glitch_reference.run::<FRACTAL_TYPE, FRACTAL_POWER>(&Arc::new(AtomicUsize::new(0)), &Arc::new(AtomicUsize::new(0)), &stop_flag);
+                match_fractal_type!(self.fractal_type, |FRACTAL_TYPE, FRACTAL_POWER| {
+                    glitch_reference.run::<FRACTAL_TYPE, FRACTAL_POWER>(&Arc::new(AtomicUsize::new(0)), &Arc::new(AtomicUsize::new(0)), handle)
+                });
 
                 self.progress.reference_count.fetch_add(1, Ordering::SeqCst);
     
-                if stop_flag.load(Ordering::SeqCst) {
+                if handle.is_cancelled() {
                     return;
                 };
 
                 pixel_data.par_iter_mut()
                     .for_each(|pixel| {
-                        // pixel.glitched = false;
+                        pixel.glitched = false;
                         pixel.delta_current -= glitch_reference_pixel.delta_current;
                         pixel.delta_reference -= glitch_reference_pixel.delta_reference;
+                        pixel.delta_current.reduce();
+                        pixel.delta_reference.reduce();
                 });
 
                 let chunk_size = max(pixel_data.len() / 512, 4);
 
+                match_fractal_type!(self.fractal_type, |FRACTAL_TYPE, FRACTAL_POWER| {
+                    match self.data_type {
+                        DataType::Distance => {
+                            Perturbation::iterate::<1, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, &self.bilinear_approximation, false, self.interior_detection, &self.pascal);
+                        },
+                        DataType::Stripe => {
+                            Perturbation::iterate::<2, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, &self.bilinear_approximation, false, self.interior_detection, &self.pascal);
+                        },
+                        DataType::DistanceStripe => {
+                            Perturbation::iterate::<3, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, &self.bilinear_approximation, false, self.interior_detection, &self.pascal);
+                        },
+                        DataType::AtomDomain => {
+                            Perturbation::iterate::<4, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, &self.bilinear_approximation, false, self.interior_detection, &self.pascal);
+                        },
+                        DataType::TriangleInequality => {
+                            Perturbation::iterate::<5, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, &self.bilinear_approximation, false, self.interior_detection, &self.pascal);
+                        },
+                        DataType::Curvature => {
+                            Perturbation::iterate::<6, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, &self.bilinear_approximation, false, self.interior_detection, &self.pascal);
+                        },
+                        _ => {
+                            Perturbation::iterate::<0, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, handle, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, &self.bilinear_approximation, false, self.interior_detection, &self.pascal);
+                        }
+                    }
+                });
 
-                match self.data_type {
-                    DataType::Distance => {
-                        Perturbation::iterate::<1, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, false, &self.pascal);
-
-                    },
-                    DataType::Stripe => {
-                        Perturbation::iterate::<2, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, false, &self.pascal);
-
-                    },
-                    DataType::DistanceStripe => {
-                        Perturbation::iterate::<3, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, false, &self.pascal);
+                let bin_pixels = pixel_data.len();
 
-                    },
-                    _ => {
-                        Perturbation::iterate::<0, FRACTAL_TYPE, FRACTAL_POWER>(pixel_data, &glitch_reference, &self.progress.iteration, &stop_flag, self.data_export.clone(), delta_pixel_extended, 1, chunk_size, &self.series_approximation, false, &self.pascal);
-                    }
-                }
+                pixel_data.retain(|packet| {
+                    packet.glitched
+                });
 
-                // pixel_data.retain(|packet| {
-                //     // packet.glitched
-                // });
+                remaining_glitched_pixels.fetch_sub(bin_pixels - pixel_data.len(), Ordering::SeqCst);
 
-                if pixel_data.len() > 0 {
-                    self.resolve_glitches(pixel_data, stop_flag, frame_time, delta_pixel_extended, Some(glitch_reference))
+                // Pixels that a new reference does not fix are never resolved, these stay marked as glitched in the export
+                if !pixel_data.is_empty() && depth < MAXIMUM_GLITCH_DEPTH {
+                    self.resolve_glitches(pixel_data, handle, delta_pixel_extended, Some(glitch_reference), remaining_glitched_pixels, maximum_glitched_pixels, depth + 1)
                 }
             });
     }
 
-    pub fn stop_rendering(&mut self, stop_flag: &Arc<AtomicBool>, frame_time: Instant) -> bool {
-        if stop_flag.load(Ordering::SeqCst) {
+    pub fn stop_rendering(&mut self, handle: &RenderHandle, frame_time: Instant) -> bool {
+        if handle.is_cancelled() {
             self.render_time = frame_time.elapsed().as_millis();
             self.progress.reset();
-            stop_flag.store(false, Ordering::SeqCst);
+            handle.clear_cancelled();
 
-            println!();
+            if let Some(observer) = &self.observer {
+                observer.frame_stopped();
+            }
 
             true
         } else {
@@ -702,7 +1299,6 @@ impl FractalRenderer {
     // find the period given a box
     pub fn find_period(&mut self) {
         self.period_finding.find_period(&self.center_reference);
-        // self.period_finding.find_atom_domain_period(&self.center_reference);
     }
 
     // Returns true if the maximum iterations has been increased
@@ -734,18 +1330,25 @@ impl FractalRenderer {
         false
     }
 
-    pub fn render(&mut self) {
-        // Print out the status information
-        if self.show_output {
-            println!(" {:<15}| {:<15}| {:<15}| {:<6}| {:<15}| {:<15}| {:<15}| {:<6}| {:<15}", "Zoom", "Approx [ms]", "Skipped [it]", "Order", "Maximum [it]", "Iteration [ms]", "Correct [ms]", "Ref", "Frame [ms]");
+    pub fn render(&mut self) -> Result<(), Error> {
+        if let Some(observer) = &self.observer {
+            observer.render_started();
         };
 
+        let output_directory = Path::new(&self.output_directory).to_owned();
+
+        if !matches!(self.data_export.lock().export_type, ExportType::Gui | ExportType::None) {
+            fs::create_dir_all(&output_directory)
+                .map_err(|error| Error::Write(self.output_directory.clone(), Box::new(error)))?;
+        }
+
+        let handle = RenderHandle::new();
         let mut count = 0;
 
         while self.remaining_frames > 0 && self.zoom.to_float() > 0.5 {
-            self.render_frame(count, 
-                format!("output/{:08}_{}", count + self.frame_offset, 
-                extended_to_string_short(self.zoom)), Arc::new(AtomicBool::new(false)));
+            let filename = output_directory.join(format!("{:08}_{}", count + self.frame_offset, extended_to_string_short(self.zoom)));
+
+            self.render_frame(count, filename.to_string_lossy().into_owned(), &handle)?;
 
             self.zoom.mantissa /= self.zoom_scale_factor;
             self.zoom.reduce();
@@ -759,7 +1362,7 @@ impl FractalRenderer {
                     // Overwrite the series approximation order
                     self.series_approximation.order = 8;
                     self.series_approximation.maximum_iteration = self.center_reference.current_iteration;
-                    self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &Arc::new(AtomicBool::new(false)));
+                    self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &handle);
                 }
 
                 // Logic in here to automatically adjust the maximum number of iterations
@@ -776,15 +1379,22 @@ impl FractalRenderer {
                 }
             } else if self.series_approximation.min_valid_iteration < 1000 && self.series_approximation.order > 16 {
                     self.series_approximation.order = 16;
-                    self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &Arc::new(AtomicBool::new(false)));
+                    self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &handle);
             } else if self.series_approximation.min_valid_iteration < 10000 && self.series_approximation.order > 32 {
                 self.series_approximation.order = 32;
-                self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &Arc::new(AtomicBool::new(false)));
+                self.series_approximation.generate_approximation(&self.center_reference, &self.progress.series_approximation, &handle);
             }
             
             self.remaining_frames -= 1;
             count += 1;
         }
+
+        Ok(())
+    }
+
+    fn jitter_distribution(jitter_factor: f64) -> Result<Normal<f64>, Error> {
+        Normal::new(0.0, jitter_factor)
+            .map_err(|error| Error::InvalidSettings(format!("jitter_factor {}: {}", jitter_factor, error)))
     }
 
     pub fn generate_render_indices(image_width: usize, image_height: usize, remove_centre: bool, zoom_scale_factor: f64, export_type: ExportType) -> Vec<usize> {
@@ -804,11 +1414,9 @@ impl FractalRenderer {
                 for (n, value) in values.iter().enumerate() {
                     for j in (0..image_height).step_by(*value) {
                         for i in (0..image_width).step_by(*value) {
-                            if n == 0 || i & (values[n - 1] - 1) != 0 || j & (values[n - 1] - 1) != 0 {
-                                if !remove_centre || (i <= val1 || i >= image_width - val1 || j <= val2 || j >= image_height - val2) {
-                                    indices.push(j * image_width + i);
-                                }
-
+                            if (n == 0 || i & (values[n - 1] - 1) != 0 || j & (values[n - 1] - 1) != 0) &&
+                                (!remove_centre || (i <= val1 || i >= image_width - val1 || j <= val2 || j >= image_height - val2)) {
+                                indices.push(j * image_width + i);
                             }
                         }
                     }
@@ -830,90 +1438,125 @@ impl FractalRenderer {
         indices
     }
 
-    pub fn regenerate_from_settings(&mut self, settings: Config) {
-        self.image_width = settings.get_int("image_width").unwrap_or(1000) as usize;
-        self.image_height = settings.get_int("image_height").unwrap_or(1000) as usize;
-        self.rotate = settings.get_float("rotate").unwrap_or(0.0).to_radians();
-        self.maximum_iteration = settings.get_int("iterations").unwrap_or(1000) as usize;
-        let initial_zoom = settings.get_string("zoom").unwrap_or_else(|_| String::from("1E0")).to_ascii_uppercase();
-        let center_real = settings.get_string("real").unwrap_or_else(|_| String::from("-0.75"));
-        let center_imag = settings.get_string("imag").unwrap_or_else(|_| String::from("0.0"));
-        let approximation_order = settings.get_int("approximation_order").unwrap_or(0) as usize;
-        self.glitch_percentage = settings.get_float("glitch_percentage").unwrap_or(0.001);
-        self.remaining_frames = settings.get_int("frames").unwrap_or(1) as usize;
-        self.frame_offset = settings.get_int("frame_offset").unwrap_or(0) as usize;
-        self.zoom_scale_factor = settings.get_float("zoom_scale").unwrap_or(2.0);
-        self.data_export.lock().display_glitches = settings.get_bool("display_glitches").unwrap_or(false);
-        self.auto_adjust_iterations = settings.get_bool("auto_adjust_iterations").unwrap_or(true);
-
-        let series_approximation_tiled = settings.get_bool("series_approximation_tiled").unwrap_or(true);
-        let series_approximation_enabled = settings.get_bool("series_approximation_enabled").unwrap_or(true);
-
-        let probe_sampling = settings.get_int("probe_sampling").unwrap_or(3) as usize;
-        self.remove_centre = settings.get_bool("remove_centre").unwrap_or(true);
-
-        self.data_export.lock().palette_iteration_span = settings.get_float("palette_iteration_span").unwrap_or(100.0) as f32;
-        self.data_export.lock().palette_offset = settings.get_float("palette_offset").unwrap_or(0.0) as f32;
-        self.data_export.lock().distance_transition = settings.get_float("distance_transition").unwrap_or(0.0) as f32;
-        self.data_export.lock().distance_color = settings.get_bool("distance_color").unwrap_or(false);
-
-        self.data_export.lock().lighting = settings.get_bool("lighting").unwrap_or(true);
-
-        let lighting_direction = settings.get_float("lighting_direction").unwrap() as f32;
-        let lighting_azimuth = settings.get_float("lighting_azimuth").unwrap() as f32;
-        let lighting_opacity = settings.get_float("lighting_opacity").unwrap() as f32;
-        let lighting_ambient = settings.get_float("lighting_ambient").unwrap() as f32;
-        let lighting_diffuse = settings.get_float("lighting_diffuse").unwrap() as f32;
-        let lighting_specular = settings.get_float("lighting_specular").unwrap() as f32;
-        let lighting_shininess = settings.get_int("lighting_shininess").unwrap() as i32;
-
-        self.data_export.lock().change_lighting(lighting_direction, lighting_azimuth, lighting_opacity, lighting_ambient, lighting_diffuse, lighting_specular, lighting_shininess);
-
-        let valid_iteration_probe_multiplier = settings.get_float("valid_iteration_probe_multiplier").unwrap_or(0.02) as f32;
-        let glitch_tolerance = settings.get_float("glitch_tolerance").unwrap_or(1.4e-6) as f64;
-        let data_storage_interval = settings.get_int("data_storage_interval").unwrap_or(10) as usize;
-        
-        self.fractal_type = get_fractal_type_from_settings(&settings);
-
-        let coloring_type = match settings.get("coloring_type").unwrap_or_else(|_| String::from("smooth_iteration")).to_ascii_uppercase().as_ref() {
-            "SMOOTH_ITERATION" | "SMOOTH" => ColoringType::SmoothIteration,
-            "STEP_ITERATION" | "STEP" => ColoringType::StepIteration,
-            "DISTANCE" => ColoringType::Distance,
-            "STRIPE" => ColoringType::Stripe,
-            "DISTANCE_STRIPE" => ColoringType::DistanceStripe,
-            _ => ColoringType::SmoothIteration
+    pub fn regenerate_from_settings(&mut self, settings: RenderSettings) -> Result<(), Error> {
+        settings.validate()?;
+
+        // The kept pixels would not match the new settings
+        self.cancelled_frame = None;
+
+        self.image_width = settings.image.image_width;
+        self.image_height = settings.image.image_height;
+        self.rotate = settings.location.rotate.to_radians();
+        self.maximum_iteration = settings.location.iterations;
+        let initial_zoom = settings.location.zoom.to_ascii_uppercase();
+        let center_real = settings.location.real.clone();
+        let center_imag = settings.location.imag.clone();
+        let julia = settings.location.julia;
+        let julia_real = &settings.location.julia_real;
+        let julia_imag = &settings.location.julia_imag;
+        let approximation_order = settings.approximation.approximation_order;
+        self.glitch_percentage = settings.approximation.glitch_percentage;
+        self.remaining_frames = settings.image.frames;
+        self.frame_offset = settings.image.frame_offset;
+        self.zoom_scale_factor = settings.image.zoom_scale;
+        self.data_export.lock().display_glitches = settings.colouring.display_glitches;
+        self.auto_adjust_iterations = settings.image.auto_adjust_iterations;
+
+        let series_approximation_tiled = settings.approximation.series_approximation_tiled;
+
+        // Either the series approximation skips the start of the orbit, or the bilinear approximation skips throughout
+        let (series_approximation_enabled, bilinear_approximation_enabled) = match settings.approximation.skip_method {
+            SkipMethod::Bilinear => (false, true),
+            SkipMethod::None => (false, false),
+            SkipMethod::Series => (settings.approximation.series_approximation_enabled, false)
         };
 
-        let pixel_data_type = match coloring_type {
-            ColoringType::SmoothIteration | ColoringType::StepIteration => DataType::Iteration,
-            ColoringType::Stripe => DataType::Stripe,
-            ColoringType::DistanceStripe => DataType::DistanceStripe,
-            _ => DataType::Distance
-        };
+        let probe_sampling = settings.approximation.probe_sampling;
+        self.remove_centre = settings.image.remove_centre;
+
+        self.data_export.lock().palette_iteration_span = settings.colouring.palette_iteration_span;
+        self.data_export.lock().palette_offset = settings.colouring.palette_offset;
+        self.data_export.lock().distance_transition = settings.colouring.distance_transition;
+        self.data_export.lock().distance_color = settings.colouring.distance_color;
+
+        let lighting = &settings.lighting;
+
+        self.data_export.lock().lighting = lighting.lighting;
+        self.data_export.lock().lighting_blend = lighting.lighting_blend;
+        self.data_export.lock().change_lighting(lighting.lighting_direction, lighting.lighting_azimuth, lighting.lighting_opacity, lighting.lighting_ambient, lighting.lighting_diffuse, lighting.lighting_specular, lighting.lighting_shininess);
 
-        self.data_export.lock().stripe_scale = settings.get_float("stripe_scale").unwrap_or(1.0) as f32;
+        let valid_iteration_probe_multiplier = settings.approximation.valid_iteration_probe_multiplier;
+        let glitch_tolerance = settings.approximation.glitch_tolerance;
+        let data_storage_interval = settings.approximation.data_storage_interval;
 
-        self.jitter = settings.get_bool("jitter").unwrap_or(false);
-        self.jitter_factor = settings.get_float("jitter_factor").unwrap_or(0.2);
-        self.show_output = settings.get_bool("show_output").unwrap_or(true);
+        self.fractal_type = settings.fractal_type();
+        self.pascal = generate_pascal_coefficients(self.fractal_type.power() + 1);
+        self.data_export.lock().fractal_type = self.fractal_type;
 
-        let mut zoom = string_to_extended(&initial_zoom);
+        let coloring_type = settings.colouring.coloring_type;
+        let pixel_data_type = coloring_type.data_type();
+        let interior_palette_buffer = settings.interior_palette_buffer()?;
+
+        self.interior_detection = settings.interior_detection();
+
+        self.data_export.lock().stripe_scale = settings.colouring.stripe_scale;
+
+        self.jitter = settings.image.jitter;
+        self.jitter_distribution = FractalRenderer::jitter_distribution(settings.image.jitter_factor)?;
+        self.export_kfr = settings.export.export_kfr;
+        self.output_directory = settings.export.output_directory.clone();
+
+        // A custom observer is kept unless the output is disabled
+        if !settings.image.show_output {
+            self.observer = None;
+        } else if self.observer.is_none() {
+            self.observer = Some(Arc::new(TerminalProgress));
+        }
+
+        let mut zoom = string_to_extended(&initial_zoom)?;
         let delta_pixel =  (-2.0 * (4.0 / self.image_height as f64 - 2.0) / zoom) / self.image_height as f64;
         let radius = delta_pixel * self.image_width as f64;
         let precision = max(64, -radius.exponent + 64);
 
         let center_location = ComplexArbitrary::with_val(
             precision as u32,
-            ComplexArbitrary::parse("(".to_owned() + &center_real + "," + &center_imag + ")").expect("provided location not valid"));
+            ComplexArbitrary::parse("(".to_owned() + &center_real + "," + &center_imag + ")")
+                .map_err(|_| Error::InvalidLocation(format!("({}, {})", center_real, center_imag)))?);
+
+        let zero = ComplexArbitrary::with_val(
+            center_location.prec().0,
+            (0.0, 0.0));
+
+        // For Julia sets the location is the starting z and the seed is used for c
+        let (reference_z, reference_c) = if julia {
+            let julia_seed = ComplexArbitrary::with_val(
+                center_location.prec().0,
+                ComplexArbitrary::parse("(".to_owned() + julia_real + "," + julia_imag + ")")
+                    .map_err(|_| Error::InvalidLocation(format!("julia seed ({}, {})", julia_real, julia_imag)))?);
+
+            (center_location, julia_seed)
+        } else {
+            (zero, center_location)
+        };
+
         let auto_approximation = get_approximation_terms(approximation_order, self.image_width, self.image_height);
 
-        self.center_reference = Reference::new(center_location.clone(), 
-            center_location, 
-            1, 
+        self.center_reference = Reference::new(reference_z, 
+            reference_c, 
+            0, 
             self.maximum_iteration, 
             data_storage_interval,
             glitch_tolerance,
-            zoom);
+            zoom,
+            julia);
+
+        self.center_reference.compact = settings.approximation.compact_reference;
+        self.reference_checkpoint_interval = settings.approximation.reference_checkpoint_interval;
+        self.reference_mode = settings.approximation.reference_mode;
+        self.reference_offset = ComplexExtended::new2(0.0, 0.0, 0);
+        self.nucleus_search_pending = self.reference_mode == ReferenceMode::Nucleus;
+
+        self.reference_file = FractalRenderer::reference_file(&settings.approximation.reference_file, &self.center_reference, self.fractal_type);
 
         self.series_approximation = SeriesApproximation::new_central(auto_approximation, 
             self.maximum_iteration, 
@@ -922,12 +1565,15 @@ impl FractalRenderer {
             series_approximation_tiled,
             series_approximation_enabled,
             valid_iteration_probe_multiplier,
-            data_storage_interval);
+            data_storage_interval,
+            self.fractal_type);
+
+        self.bilinear_approximation = BilinearApproximation::new(bilinear_approximation_enabled);
 
         let mut data_export = self.data_export.lock();
 
         if self.image_width != data_export.image_width || self.image_height != data_export.image_height {
-            self.render_indices = FractalRenderer::generate_render_indices(self.image_width, self.image_height, self.remove_centre, self.zoom_scale_factor, self.data_export.lock().export_type);
+            self.render_indices = FractalRenderer::generate_render_indices(self.image_width, self.image_height, self.remove_centre, self.zoom_scale_factor, data_export.export_type);
             data_export.centre_removed = self.remove_centre;
         }
 
@@ -940,6 +1586,8 @@ impl FractalRenderer {
         }
 
         self.zoom = zoom;
+        self.center_real = center_real;
+        self.center_imag = center_imag;
 
         self.progress.reset_all(self.maximum_iteration);
 
@@ -947,7 +1595,11 @@ impl FractalRenderer {
         data_export.image_height = self.image_height;
         data_export.data_type = pixel_data_type;
         data_export.coloring_type = coloring_type;
+        data_export.interior_coloring = settings.colouring.interior_coloring;
+        data_export.interior_palette_buffer = interior_palette_buffer;
 
         data_export.clear_buffers();
+
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,694 @@
+use crate::Error;
+use crate::util::{FractalType, MAXIMUM_FRACTAL_POWER, generate_default_interior_palette, generate_default_palette, string_to_extended};
+use crate::util::data_export::{ColoringType, ExportType, InteriorColoring, LightingBlend};
+
+use colorgrad::{BlendMode, Color, CustomGradient, Interpolation};
+use config::{Config, Source};
+use serde::{Deserialize, Serialize};
+
+use std::convert::TryFrom;
+
+// All of the settings used by the renderer. The sections are flattened, so the settings are read from
+// (and written to) a single table of keys, which is the layout used by the location and options files.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RenderSettings {
+    #[serde(flatten)]
+    pub location: LocationSettings,
+    #[serde(flatten)]
+    pub image: ImageSettings,
+    #[serde(flatten)]
+    pub approximation: ApproximationSettings,
+    #[serde(flatten)]
+    pub colouring: ColouringSettings,
+    #[serde(flatten)]
+    pub lighting: LightingSettings,
+    #[serde(flatten)]
+    pub export: ExportSettings,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LocationSettings {
+    pub real: String,
+    pub imag: String,
+    pub zoom: String,
+    pub iterations: usize,
+    // Rotation in degrees
+    pub rotate: f64,
+    pub fractal_type: FractalFormula,
+    pub fractal_power: usize,
+    pub julia: bool,
+    pub julia_real: String,
+    pub julia_imag: String,
+}
+
+impl Default for LocationSettings {
+    fn default() -> Self {
+        LocationSettings {
+            real: String::from("-0.75"),
+            imag: String::from("0.0"),
+            zoom: String::from("1E0"),
+            iterations: 1000,
+            rotate: 0.0,
+            fractal_type: FractalFormula::Mandelbrot,
+            fractal_power: 2,
+            julia: false,
+            julia_real: String::from("0.0"),
+            julia_imag: String::from("0.0"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ImageSettings {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub frames: usize,
+    pub frame_offset: usize,
+    pub zoom_scale: f64,
+    pub remove_centre: bool,
+    pub auto_adjust_iterations: bool,
+    pub jitter: bool,
+    pub jitter_factor: f64,
+    pub show_output: bool,
+}
+
+impl Default for ImageSettings {
+    fn default() -> Self {
+        ImageSettings {
+            image_width: 1000,
+            image_height: 1000,
+            frames: 1,
+            frame_offset: 0,
+            zoom_scale: 2.0,
+            remove_centre: false,
+            auto_adjust_iterations: true,
+            jitter: false,
+            jitter_factor: 0.2,
+            show_output: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ApproximationSettings {
+    pub skip_method: SkipMethod,
+    pub reference_mode: ReferenceMode,
+    // An order of 0 selects the number of terms automatically from the image size
+    pub approximation_order: usize,
+    pub probe_sampling: usize,
+    pub series_approximation_tiled: bool,
+    pub series_approximation_enabled: bool,
+    pub valid_iteration_probe_multiplier: f32,
+    pub data_storage_interval: usize,
+    pub glitch_tolerance: f64,
+    // Percentage of the pixels that are allowed to remain glitched
+    pub glitch_percentage: f64,
+    // The reference orbit is loaded from this file when it matches the location, and saved to it otherwise
+    pub reference_file: String,
+    // Stores the extended precision orbit only where it underflows, which uses much less memory at high iteration counts
+    pub compact_reference: bool,
+    // Seconds between saving the incomplete reference orbit to the reference file, 0 disables checkpoints
+    pub reference_checkpoint_interval: u64,
+    // Stops iterating pixels once they are found to be in an attracting cycle
+    pub interior_detection: bool,
+}
+
+impl Default for ApproximationSettings {
+    fn default() -> Self {
+        ApproximationSettings {
+            skip_method: SkipMethod::Series,
+            reference_mode: ReferenceMode::Centre,
+            approximation_order: 0,
+            probe_sampling: 3,
+            series_approximation_tiled: true,
+            series_approximation_enabled: true,
+            valid_iteration_probe_multiplier: 0.02,
+            data_storage_interval: 10,
+            glitch_tolerance: 1.4e-6,
+            glitch_percentage: 0.001,
+            reference_file: String::new(),
+            compact_reference: false,
+            reference_checkpoint_interval: 0,
+            interior_detection: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ColouringSettings {
+    pub coloring_type: ColoringType,
+    // Red, green and blue values for each colour of the palette
+    pub palette: Option<Vec<u8>>,
+    pub palette_iteration_span: f32,
+    pub palette_offset: f32,
+    pub palette_cyclic: bool,
+    pub distance_color: bool,
+    pub distance_transition: f32,
+    pub stripe_scale: f32,
+    pub display_glitches: bool,
+    pub interior_coloring: InteriorColoring,
+    // Red, green and blue values for each colour of the interior palette, which goes from the boundary inwards
+    pub interior_palette: Option<Vec<u8>>,
+}
+
+impl Default for ColouringSettings {
+    fn default() -> Self {
+        ColouringSettings {
+            coloring_type: ColoringType::SmoothIteration,
+            palette: None,
+            palette_iteration_span: 100.0,
+            palette_offset: 0.0,
+            palette_cyclic: true,
+            distance_color: false,
+            distance_transition: 0.0,
+            stripe_scale: 1.0,
+            display_glitches: false,
+            interior_coloring: InteriorColoring::Black,
+            interior_palette: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LightingSettings {
+    pub lighting: bool,
+    pub lighting_direction: f32,
+    pub lighting_azimuth: f32,
+    pub lighting_opacity: f32,
+    pub lighting_ambient: f32,
+    pub lighting_diffuse: f32,
+    pub lighting_specular: f32,
+    pub lighting_shininess: i32,
+    pub lighting_blend: LightingBlend,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        LightingSettings {
+            lighting: true,
+            lighting_direction: 30.0,
+            lighting_azimuth: 35.0,
+            lighting_opacity: 0.75,
+            lighting_ambient: 0.4,
+            lighting_diffuse: 0.5,
+            lighting_specular: 0.5,
+            lighting_shininess: 20,
+            lighting_blend: LightingBlend::Overlay,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExportSettings {
+    pub export: ExportType,
+    pub export_kfr: bool,
+    // Directory that the frames are written to by render
+    pub output_directory: String,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            export: ExportType::Color,
+            export_kfr: false,
+            output_directory: String::from("output"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum FractalFormula {
+    Mandelbrot,
+    BurningShip,
+    Tricorn,
+    Celtic,
+    Buffalo,
+    PerpendicularBurningShip,
+}
+
+impl TryFrom<String> for FractalFormula {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_ascii_uppercase().as_ref() {
+            "MANDELBROT" => Ok(FractalFormula::Mandelbrot),
+            "BURNINGSHIP" => Ok(FractalFormula::BurningShip),
+            "TRICORN" | "MANDELBAR" => Ok(FractalFormula::Tricorn),
+            "CELTIC" => Ok(FractalFormula::Celtic),
+            "BUFFALO" => Ok(FractalFormula::Buffalo),
+            "PERPENDICULARBURNINGSHIP" | "PERPENDICULAR" => Ok(FractalFormula::PerpendicularBurningShip),
+            _ => Err(format!("unknown fractal type {}", value))
+        }
+    }
+}
+
+impl From<FractalFormula> for String {
+    fn from(value: FractalFormula) -> Self {
+        match value {
+            FractalFormula::Mandelbrot => "mandelbrot",
+            FractalFormula::BurningShip => "burningship",
+            FractalFormula::Tricorn => "tricorn",
+            FractalFormula::Celtic => "celtic",
+            FractalFormula::Buffalo => "buffalo",
+            FractalFormula::PerpendicularBurningShip => "perpendicular",
+        }.to_owned()
+    }
+}
+
+// Either the series approximation skips the start of the orbit, or the bilinear approximation skips throughout
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SkipMethod {
+    Series,
+    Bilinear,
+    None,
+}
+
+impl TryFrom<String> for SkipMethod {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_ascii_uppercase().as_ref() {
+            "SERIES" | "SA" => Ok(SkipMethod::Series),
+            "BLA" | "BILINEAR" => Ok(SkipMethod::Bilinear),
+            "NONE" => Ok(SkipMethod::None),
+            _ => Err(format!("unknown skip method {}", value))
+        }
+    }
+}
+
+impl From<SkipMethod> for String {
+    fn from(value: SkipMethod) -> Self {
+        match value {
+            SkipMethod::Series => "series",
+            SkipMethod::Bilinear => "bla",
+            SkipMethod::None => "none",
+        }.to_owned()
+    }
+}
+
+// The primary reference is either the image centre, or the nucleus of the lowest period atom in the image
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ReferenceMode {
+    Centre,
+    Nucleus,
+}
+
+impl TryFrom<String> for ReferenceMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_ascii_uppercase().as_ref() {
+            "CENTRE" | "CENTER" => Ok(ReferenceMode::Centre),
+            "NUCLEUS" => Ok(ReferenceMode::Nucleus),
+            _ => Err(format!("unknown reference mode {}", value))
+        }
+    }
+}
+
+impl From<ReferenceMode> for String {
+    fn from(value: ReferenceMode) -> Self {
+        match value {
+            ReferenceMode::Centre => "centre",
+            ReferenceMode::Nucleus => "nucleus",
+        }.to_owned()
+    }
+}
+
+impl RenderSettings {
+    pub fn builder() -> RenderSettingsBuilder {
+        RenderSettingsBuilder {
+            settings: RenderSettings::default()
+        }
+    }
+
+    // Reads the settings from merged location and options files, which have the keys at the top level
+    pub fn from_config(config: Config) -> Result<Self, Error> {
+        // The palettes are checked first, as the deserialize error for a colour value does not give the key
+        for key in ["palette", "interior_palette"] {
+            if let Ok(values) = config.get_array(key) {
+                if values.into_iter().any(|value| !matches!(value.into_int(), Ok(0..=255))) {
+                    return Err(Error::InvalidPalette(format!("{} values must be integers from 0 to 255", key)));
+                }
+            }
+        }
+
+        let settings = config.try_deserialize::<RenderSettings>()
+            .map_err(|error| Error::InvalidSettings(error.to_string()))?;
+
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    // The keys in the config that are not used by the renderer, which are usually typos
+    pub fn unknown_keys(config: &Config) -> Vec<String> {
+        let known_keys = Config::try_from(&RenderSettings::default())
+            .and_then(|known| known.collect())
+            .unwrap_or_default();
+
+        let mut unknown_keys = config.collect()
+            .unwrap_or_default()
+            .into_keys()
+            .filter(|key| !known_keys.contains_key(key))
+            .collect::<Vec<String>>();
+
+        unknown_keys.sort();
+        unknown_keys
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |reason: String| Err(Error::InvalidSettings(reason));
+
+        let zoom = string_to_extended(&self.location.zoom)?;
+
+        if zoom.mantissa <= 0.0 {
+            return Err(Error::InvalidZoom(self.location.zoom.clone()));
+        }
+
+        if self.location.iterations < 2 {
+            return invalid(format!("iterations must be at least 2, found {}", self.location.iterations));
+        }
+
+        if !(2..=MAXIMUM_FRACTAL_POWER).contains(&self.location.fractal_power) {
+            return invalid(format!("fractal_power must be from 2 to {}, found {}", MAXIMUM_FRACTAL_POWER, self.location.fractal_power));
+        }
+
+        // Only the mandelbrot has higher power formulas, the other variants are all quadratic
+        if self.location.fractal_type != FractalFormula::Mandelbrot && self.location.fractal_power != 2 {
+            return invalid(format!("fractal_power {} is only supported for the mandelbrot, found fractal_type {}", self.location.fractal_power, String::from(self.location.fractal_type)));
+        }
+
+        if self.image.image_width == 0 || self.image.image_height == 0 {
+            return invalid(format!("image size must be at least 1x1, found {}x{}", self.image.image_width, self.image.image_height));
+        }
+
+        if self.image.zoom_scale <= 0.0 {
+            return invalid(format!("zoom_scale must be positive, found {}", self.image.zoom_scale));
+        }
+
+        if !self.image.jitter_factor.is_finite() || self.image.jitter_factor < 0.0 {
+            return invalid(format!("jitter_factor must be a finite value that is not negative, found {}", self.image.jitter_factor));
+        }
+
+        if self.approximation.probe_sampling < 2 {
+            return invalid(format!("probe_sampling must be at least 2, found {}", self.approximation.probe_sampling));
+        }
+
+        if self.approximation.data_storage_interval == 0 {
+            return invalid(String::from("data_storage_interval must be at least 1, found 0"));
+        }
+
+        if self.approximation.valid_iteration_probe_multiplier <= 0.0 || self.approximation.valid_iteration_probe_multiplier > 1.0 {
+            return invalid(format!("valid_iteration_probe_multiplier must be in (0, 1], found {}", self.approximation.valid_iteration_probe_multiplier));
+        }
+
+        if self.approximation.glitch_tolerance <= 0.0 {
+            return invalid(format!("glitch_tolerance must be positive, found {}", self.approximation.glitch_tolerance));
+        }
+
+        if !(0.0..=100.0).contains(&self.approximation.glitch_percentage) {
+            return invalid(format!("glitch_percentage must be from 0 to 100, found {}", self.approximation.glitch_percentage));
+        }
+
+        // The period and nucleus finding are only implemented for the power 2 mandelbrot
+        if self.approximation.reference_mode == ReferenceMode::Nucleus && (self.fractal_type() != FractalType::Mandelbrot(2) || self.location.julia) {
+            return invalid(String::from("reference_mode nucleus needs the power 2 mandelbrot without julia"));
+        }
+
+        // The attracting cycle is found with the derivatives of the mandelbrot formula
+        if self.interior_detection() && (!matches!(self.fractal_type(), FractalType::Mandelbrot(_)) || self.location.julia) {
+            return invalid(String::from("interior_detection and interior_coloring distance need the mandelbrot without julia"));
+        }
+
+        if self.approximation.reference_checkpoint_interval > 0 && self.approximation.reference_file.is_empty() {
+            return invalid(String::from("reference_checkpoint_interval needs a reference_file to save the checkpoints to"));
+        }
+
+        if self.colouring.palette_iteration_span <= 0.0 {
+            return invalid(format!("palette_iteration_span must be positive, found {}", self.colouring.palette_iteration_span));
+        }
+
+        if !(0.0..=1.0).contains(&self.lighting.lighting_opacity) {
+            return invalid(format!("lighting_opacity must be from 0 to 1, found {}", self.lighting.lighting_opacity));
+        }
+
+        if self.export.output_directory.is_empty() {
+            return invalid(String::from("output_directory must not be empty"));
+        }
+
+        if let Some(palette) = &self.colouring.palette {
+            if palette.is_empty() || palette.len() % 3 != 0 {
+                return Err(Error::InvalidPalette(format!("expected red, green and blue values for each colour, found {} values", palette.len())));
+            }
+        }
+
+        if let Some(palette) = &self.colouring.interior_palette {
+            if palette.len() < 6 || palette.len() % 3 != 0 {
+                return Err(Error::InvalidPalette(format!("expected red, green and blue values for at least two interior colours, found {} values", palette.len())));
+            }
+        }
+
+        Ok(())
+    }
+
+    // The power is only checked by validate, so this should not be called on unvalidated settings
+    pub fn fractal_type(&self) -> FractalType {
+        let power = self.location.fractal_power;
+
+        match self.location.fractal_type {
+            FractalFormula::Mandelbrot => FractalType::Mandelbrot(power),
+            FractalFormula::BurningShip => FractalType::BurningShip(power),
+            FractalFormula::Tricorn => FractalType::Tricorn(power),
+            FractalFormula::Celtic => FractalType::Celtic(power),
+            FractalFormula::Buffalo => FractalType::Buffalo(power),
+            FractalFormula::PerpendicularBurningShip => FractalType::PerpendicularBurningShip(power),
+        }
+    }
+
+    // The interior distance needs the attracting cycle, which is found by the interior detection
+    pub fn interior_detection(&self) -> bool {
+        self.approximation.interior_detection || self.colouring.interior_coloring == InteriorColoring::Distance
+    }
+
+    // Returns the interpolated interior palette, falling back to the default interior palette
+    pub fn interior_palette_buffer(&self) -> Result<Vec<Color>, Error> {
+        let palette = match &self.colouring.interior_palette {
+            Some(palette) if !palette.is_empty() => palette,
+            _ => return Ok(generate_default_interior_palette())
+        };
+
+        if palette.len() < 6 || palette.len() % 3 != 0 {
+            return Err(Error::InvalidPalette(format!("expected red, green and blue values for at least two interior colours, found {} values", palette.len())));
+        }
+
+        let colors = palette.chunks_exact(3)
+            .map(|value| Color::from_rgba8(value[0], value[1], value[2], 255))
+            .collect::<Vec<Color>>();
+
+        let palette_generator = CustomGradient::new()
+            .colors(&colors)
+            .interpolation(Interpolation::CatmullRom)
+            .mode(BlendMode::Oklab)
+            .build()
+            .map_err(|error| Error::InvalidPalette(error.to_string()))?;
+
+        Ok(palette_generator.colors(colors.len() * 64))
+    }
+
+    // Returns the palette colours and the interpolated palette, falling back to the default palette
+    pub fn palette_buffers(&self) -> Result<(Vec<Color>, Vec<Color>), Error> {
+        let palette = match &self.colouring.palette {
+            Some(palette) if !palette.is_empty() => palette,
+            _ => return Ok(generate_default_palette())
+        };
+
+        if palette.len() % 3 != 0 {
+            return Err(Error::InvalidPalette(format!("expected red, green and blue values for each colour, found {} values", palette.len())));
+        }
+
+        let mut colors = palette.chunks_exact(3)
+            .map(|value| Color::from_rgba8(value[0], value[1], value[2], 255))
+            .collect::<Vec<Color>>();
+
+        if colors[0] != *colors.last().unwrap() {
+            colors.push(colors[0].clone());
+        };
+
+        let mut number_colors = colors.len();
+
+        if self.colouring.palette_cyclic {
+            number_colors -= 1;
+        }
+
+        let palette_generator = CustomGradient::new()
+            .colors(&colors[0..number_colors])
+            .interpolation(Interpolation::CatmullRom)
+            .mode(BlendMode::Oklab)
+            .build()
+            .map_err(|error| Error::InvalidPalette(error.to_string()))?;
+
+        Ok((colors, palette_generator.colors(number_colors * 64)))
+    }
+}
+
+pub struct RenderSettingsBuilder {
+    settings: RenderSettings,
+}
+
+impl RenderSettingsBuilder {
+    pub fn location(mut self, real: &str, imag: &str, zoom: &str) -> Self {
+        self.settings.location.real = real.to_owned();
+        self.settings.location.imag = imag.to_owned();
+        self.settings.location.zoom = zoom.to_owned();
+        self
+    }
+
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.settings.location.iterations = iterations;
+        self
+    }
+
+    pub fn rotate(mut self, degrees: f64) -> Self {
+        self.settings.location.rotate = degrees;
+        self
+    }
+
+    pub fn fractal_type(mut self, fractal_type: FractalFormula, fractal_power: usize) -> Self {
+        self.settings.location.fractal_type = fractal_type;
+        self.settings.location.fractal_power = fractal_power;
+        self
+    }
+
+    pub fn julia(mut self, julia_real: &str, julia_imag: &str) -> Self {
+        self.settings.location.julia = true;
+        self.settings.location.julia_real = julia_real.to_owned();
+        self.settings.location.julia_imag = julia_imag.to_owned();
+        self
+    }
+
+    pub fn image_size(mut self, image_width: usize, image_height: usize) -> Self {
+        self.settings.image.image_width = image_width;
+        self.settings.image.image_height = image_height;
+        self
+    }
+
+    pub fn frames(mut self, frames: usize, zoom_scale: f64) -> Self {
+        self.settings.image.frames = frames;
+        self.settings.image.zoom_scale = zoom_scale;
+        self
+    }
+
+    pub fn show_output(mut self, show_output: bool) -> Self {
+        self.settings.image.show_output = show_output;
+        self
+    }
+
+    pub fn skip_method(mut self, skip_method: SkipMethod) -> Self {
+        self.settings.approximation.skip_method = skip_method;
+        self
+    }
+
+    pub fn reference_mode(mut self, reference_mode: ReferenceMode) -> Self {
+        self.settings.approximation.reference_mode = reference_mode;
+        self
+    }
+
+    pub fn approximation_order(mut self, approximation_order: usize) -> Self {
+        self.settings.approximation.approximation_order = approximation_order;
+        self
+    }
+
+    pub fn reference_file(mut self, reference_file: &str) -> Self {
+        self.settings.approximation.reference_file = reference_file.to_owned();
+        self
+    }
+
+    pub fn reference_checkpoint_interval(mut self, seconds: u64) -> Self {
+        self.settings.approximation.reference_checkpoint_interval = seconds;
+        self
+    }
+
+    pub fn compact_reference(mut self, compact_reference: bool) -> Self {
+        self.settings.approximation.compact_reference = compact_reference;
+        self
+    }
+
+    pub fn coloring_type(mut self, coloring_type: ColoringType) -> Self {
+        self.settings.colouring.coloring_type = coloring_type;
+        self
+    }
+
+    pub fn palette(mut self, palette: &[(u8, u8, u8)]) -> Self {
+        self.settings.colouring.palette = Some(palette.iter().flat_map(|&(r, g, b)| [r, g, b]).collect());
+        self
+    }
+
+    pub fn interior_coloring(mut self, interior_coloring: InteriorColoring) -> Self {
+        self.settings.colouring.interior_coloring = interior_coloring;
+        self
+    }
+
+    pub fn interior_palette(mut self, palette: &[(u8, u8, u8)]) -> Self {
+        self.settings.colouring.interior_palette = Some(palette.iter().flat_map(|&(r, g, b)| [r, g, b]).collect());
+        self
+    }
+
+    pub fn interior_detection(mut self, interior_detection: bool) -> Self {
+        self.settings.approximation.interior_detection = interior_detection;
+        self
+    }
+
+    pub fn palette_iteration_span(mut self, palette_iteration_span: f32, palette_offset: f32) -> Self {
+        self.settings.colouring.palette_iteration_span = palette_iteration_span;
+        self.settings.colouring.palette_offset = palette_offset;
+        self
+    }
+
+    pub fn lighting(mut self, lighting: LightingSettings) -> Self {
+        self.settings.lighting = lighting;
+        self
+    }
+
+    pub fn export(mut self, export: ExportType, export_kfr: bool) -> Self {
+        self.settings.export.export = export;
+        self.settings.export.export_kfr = export_kfr;
+        self
+    }
+
+    pub fn output_directory(mut self, output_directory: &str) -> Self {
+        self.settings.export.output_directory = output_directory.to_owned();
+        self
+    }
+
+    // Sections can be replaced as a whole for the less common settings
+    pub fn approximation_settings(mut self, approximation: ApproximationSettings) -> Self {
+        self.settings.approximation = approximation;
+        self
+    }
+
+    pub fn image_settings(mut self, image: ImageSettings) -> Self {
+        self.settings.image = image;
+        self
+    }
+
+    pub fn colouring_settings(mut self, colouring: ColouringSettings) -> Self {
+        self.settings.colouring = colouring;
+        self
+    }
+
+    pub fn build(self) -> Result<RenderSettings, Error> {
+        self.settings.validate()?;
+
+        Ok(self.settings)
+    }
+}
@@ -27,6 +27,33 @@ impl ComplexExtended {
         }
     }
 
+    // Combines separate real and imaginary parts, which can have very different exponents
+    #[inline]
+    pub fn from_parts(re: FloatExtended, im: FloatExtended) -> Self {
+        let exponent = if re.mantissa == 0.0 {
+            im.exponent
+        } else if im.mantissa == 0.0 || re.exponent > im.exponent {
+            re.exponent
+        } else {
+            im.exponent
+        };
+
+        ComplexExtended {
+            mantissa: Complex::<f64>::new(re.mantissa.ldexp(re.exponent - exponent), im.mantissa.ldexp(im.exponent - exponent)),
+            exponent
+        }
+    }
+
+    #[inline]
+    pub fn real(&self) -> FloatExtended {
+        FloatExtended::new(self.mantissa.re, self.exponent)
+    }
+
+    #[inline]
+    pub fn imag(&self) -> FloatExtended {
+        FloatExtended::new(self.mantissa.im, self.exponent)
+    }
+
     #[inline]
     pub fn norm(&self) -> FloatExtended {
         FloatExtended {
@@ -75,7 +102,7 @@ impl ComplexExtended {
 
     #[inline]
     pub fn scale_to_exponent(&mut self, exponent: i32) {
-        let added_exponent = exponent - self.exponent;
+        let added_exponent = self.exponent - exponent;
         self.mantissa.re = self.mantissa.re.ldexp(added_exponent);
         self.mantissa.im = self.mantissa.im.ldexp(added_exponent);
         self.exponent = exponent;
@@ -1,37 +1,201 @@
 use crate::math::Reference;
-use crate::util::{ComplexFixed, FloatExtended, FractalType, PixelData};
+use crate::util::{ComplexFixed, FloatExtended, FractalType, PixelData, generate_default_interior_palette};
 
-use std::{collections::HashMap, f64::consts::LN_2};
+use std::{collections::HashMap, convert::TryFrom, f64::consts::LN_2};
 // use std::cmp::{min, max};
-use std::f32::consts::FRAC_PI_4;
+use std::f32::consts::{FRAC_PI_4, PI};
 
 use exr::prelude::*;
 
 use colorgrad::{BlendMode, Color, CustomGradient, Interpolation};
+use serde::{Deserialize, Serialize};
 
 // This is 1e16f32.ln().log2() + 1.0
-const ESCAPE_RADIUS_LN_LOG2_P1: f32 = 5.203254472696 + 1.0;
+const ESCAPE_RADIUS_LN_LOG2_P1: f32 = 5.203_254_7 + 1.0;
 // const ESCAPE_RADIUS_LN_LOG2_P1: f32 = 7.203254472699 + 1.0;
 // const ESCAPE_RADIUS_LN_LOG2_P1: f32 = 8.2032544726997 + 1.0;
 
 // const ESCAPE_RADIUS_LN_LOG2_P1: f32 = 3.282888062227 + 1.0;
 // const ESCAPE_RADIUS_LN_LOG2_P1: f32 = 2.601627236349860 + 1.0;
 
-#[derive(PartialEq, Clone, Copy)]
+// Spreads consecutive atom domain periods across the palette
+const GOLDEN_RATIO_CONJUGATE: f32 = 0.618034;
+
+// Amount of the atom domain colour mixed into the iteration colour
+const ATOM_DOMAIN_TINT: f64 = 0.4;
+
+// The interior palette is spread over distances up to 2^8 pixels from the boundary
+const INTERIOR_DISTANCE_OCTAVES: f32 = 8.0;
+
+#[derive(PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum ExportType {
     Color,
     Raw,
     Both,
+    Kfb,
     Gui,
+    None,
 }
 
-#[derive(PartialEq, Clone, Copy)]
+impl TryFrom<String> for ExportType {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.to_ascii_uppercase().as_ref() {
+            "COLOUR" | "COLOR" | "PNG" | "JPG" | "JPEG" => Ok(ExportType::Color),
+            "RAW" | "EXR" => Ok(ExportType::Raw),
+            "BOTH" => Ok(ExportType::Both),
+            "KFB" => Ok(ExportType::Kfb),
+            "GUI" => Ok(ExportType::Gui),
+            "NONE" => Ok(ExportType::None),
+            _ => Err(format!("unknown export type {}", value))
+        }
+    }
+}
+
+impl From<ExportType> for String {
+    fn from(value: ExportType) -> Self {
+        match value {
+            ExportType::Color => "png",
+            ExportType::Raw => "exr",
+            ExportType::Both => "both",
+            ExportType::Kfb => "kfb",
+            ExportType::Gui => "gui",
+            ExportType::None => "none",
+        }.to_owned()
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum ColoringType {
     SmoothIteration,
     StepIteration,
     Distance,
     DistanceStripe,
     Stripe,
+    AtomDomain,
+    AtomDomainIteration,
+    TriangleInequality,
+    Curvature,
+}
+
+impl ColoringType {
+    // The data that needs to be calculated for each pixel for this colouring
+    pub fn data_type(&self) -> DataType {
+        match self {
+            ColoringType::SmoothIteration | ColoringType::StepIteration => DataType::Iteration,
+            ColoringType::Stripe => DataType::Stripe,
+            ColoringType::DistanceStripe => DataType::DistanceStripe,
+            ColoringType::AtomDomain | ColoringType::AtomDomainIteration => DataType::AtomDomain,
+            ColoringType::TriangleInequality => DataType::TriangleInequality,
+            ColoringType::Curvature => DataType::Curvature,
+            _ => DataType::Distance
+        }
+    }
+}
+
+impl TryFrom<String> for ColoringType {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.to_ascii_uppercase().as_ref() {
+            "SMOOTH_ITERATION" | "SMOOTH" | "ITERATION" => Ok(ColoringType::SmoothIteration),
+            "STEP_ITERATION" | "STEP" => Ok(ColoringType::StepIteration),
+            "DISTANCE" => Ok(ColoringType::Distance),
+            "STRIPE" => Ok(ColoringType::Stripe),
+            "DISTANCE_STRIPE" => Ok(ColoringType::DistanceStripe),
+            "ATOM_DOMAIN" | "ATOM" => Ok(ColoringType::AtomDomain),
+            "ATOM_DOMAIN_ITERATION" => Ok(ColoringType::AtomDomainIteration),
+            "TRIANGLE_INEQUALITY" | "TIA" => Ok(ColoringType::TriangleInequality),
+            "CURVATURE" => Ok(ColoringType::Curvature),
+            _ => Err(format!("unknown coloring type {}", value))
+        }
+    }
+}
+
+impl From<ColoringType> for String {
+    fn from(value: ColoringType) -> Self {
+        match value {
+            ColoringType::SmoothIteration => "smooth_iteration",
+            ColoringType::StepIteration => "step_iteration",
+            ColoringType::Distance => "distance",
+            ColoringType::DistanceStripe => "distance_stripe",
+            ColoringType::Stripe => "stripe",
+            ColoringType::AtomDomain => "atom_domain",
+            ColoringType::AtomDomainIteration => "atom_domain_iteration",
+            ColoringType::TriangleInequality => "triangle_inequality",
+            ColoringType::Curvature => "curvature",
+        }.to_owned()
+    }
+}
+
+// Colouring of the pixels that reach the maximum iteration
+#[derive(PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum InteriorColoring {
+    Black,
+    Distance,
+    FinalMagnitude,
+    FinalAngle,
+}
+
+impl TryFrom<String> for InteriorColoring {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.to_ascii_uppercase().as_ref() {
+            "BLACK" | "NONE" => Ok(InteriorColoring::Black),
+            "DISTANCE" => Ok(InteriorColoring::Distance),
+            "FINAL_MAGNITUDE" | "MAGNITUDE" => Ok(InteriorColoring::FinalMagnitude),
+            "FINAL_ANGLE" | "ANGLE" => Ok(InteriorColoring::FinalAngle),
+            _ => Err(format!("unknown interior coloring {}", value))
+        }
+    }
+}
+
+impl From<InteriorColoring> for String {
+    fn from(value: InteriorColoring) -> Self {
+        match value {
+            InteriorColoring::Black => "black",
+            InteriorColoring::Distance => "distance",
+            InteriorColoring::FinalMagnitude => "final_magnitude",
+            InteriorColoring::FinalAngle => "final_angle",
+        }.to_owned()
+    }
+}
+
+// How the Blinn-Phong lighting is combined with the palette colour of the distance colouring
+#[derive(PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum LightingBlend {
+    Overlay,
+    Multiply,
+    SoftLight,
+}
+
+impl TryFrom<String> for LightingBlend {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        match value.to_ascii_uppercase().as_ref() {
+            "OVERLAY" => Ok(LightingBlend::Overlay),
+            "MULTIPLY" => Ok(LightingBlend::Multiply),
+            "SOFT_LIGHT" | "SOFTLIGHT" => Ok(LightingBlend::SoftLight),
+            _ => Err(format!("unknown lighting blend {}", value))
+        }
+    }
+}
+
+impl From<LightingBlend> for String {
+    fn from(value: LightingBlend) -> Self {
+        match value {
+            LightingBlend::Overlay => "overlay",
+            LightingBlend::Multiply => "multiply",
+            LightingBlend::SoftLight => "soft_light",
+        }.to_owned()
+    }
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -41,6 +205,8 @@ pub enum DataType {
     Stripe,
     DistanceStripe,
     AtomDomain,
+    TriangleInequality,
+    Curvature,
 }
 
 pub struct LightingParameters {
@@ -85,6 +251,37 @@ impl LightingParameters {
     }
 }
 
+// A copy of the output of a rendered frame, for using the results without writing any files
+#[derive(Clone, Debug)]
+pub struct FrameData {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub maximum_iteration: usize,
+    // Red, green and blue values for each pixel, in rows from the top left
+    pub rgb: Vec<u8>,
+    pub iterations: Vec<u32>,
+    pub smooth: Vec<f32>,
+    // Distance estimate in pixels, only calculated for the distance colourings
+    pub distance: Vec<f32>,
+    // Atom domain period, only calculated for the atom domain colourings
+    pub atom_domain: Vec<u32>,
+    // Triangle inequality or curvature average, only calculated for those colourings
+    pub average: Vec<f32>,
+    // Interior distance estimate in pixels, final |z| or final angle of z for the interior pixels, depending on the interior colouring
+    pub interior: Vec<f32>,
+}
+
+impl FrameData {
+    pub fn to_image(&self) -> crate::Result<image::RgbImage> {
+        image::RgbImage::from_raw(self.image_width as u32, self.image_height as u32, self.rgb.clone())
+            .ok_or_else(|| crate::Error::InvalidSettings(format!("colour buffer of {} values does not match the image size {}x{}", self.rgb.len(), self.image_width, self.image_height)))
+    }
+
+    pub fn is_interior(&self, k: usize) -> bool {
+        self.iterations[k] as usize >= self.maximum_iteration
+    }
+}
+
 pub struct DataExport {
     pub image_width: usize,
     pub image_height: usize,
@@ -94,9 +291,14 @@ pub struct DataExport {
     pub stripe: Vec<f32>,
     pub distance_x: Vec<f32>,
     pub distance_y: Vec<f32>,
-    // pub glitched: Vec<bool>,
+    pub atom_domain: Vec<u32>,
+    pub average: Vec<f32>,
+    pub interior: Vec<f32>,
+    pub glitched: Vec<bool>,
     pub palette_buffer: Vec<Color>,
     pub palette_interpolated_buffer: Vec<Color>,
+    pub interior_palette_buffer: Vec<Color>,
+    pub interior_coloring: InteriorColoring,
     pub palette_cyclic: bool,
     pub display_glitches: bool,
     pub palette_iteration_span: f32,
@@ -110,11 +312,13 @@ pub struct DataExport {
     pub export_type: ExportType,
     pub lighting_parameters: LightingParameters,
     pub lighting: bool,
+    pub lighting_blend: LightingBlend,
     pub distance_color: bool,
     pub stripe_scale: f32,
 }
 
 impl DataExport {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         image_width: usize,
         image_height: usize,
@@ -143,9 +347,14 @@ impl DataExport {
             stripe: vec![0.0f32; image_width * image_height],
             distance_x: vec![0.0f32; image_width * image_height],
             distance_y: vec![0.0f32; image_width * image_height],
-            // glitched: vec![false; image_width * image_height],
+            atom_domain: vec![0u32; image_width * image_height],
+            average: vec![0.0f32; image_width * image_height],
+            interior: vec![0.0f32; image_width * image_height],
+            glitched: vec![false; image_width * image_height],
             palette_buffer,
             palette_interpolated_buffer,
+            interior_palette_buffer: generate_default_interior_palette(),
+            interior_coloring: InteriorColoring::Black,
             palette_cyclic,
             display_glitches,
             palette_iteration_span,
@@ -159,6 +368,7 @@ impl DataExport {
             export_type,
             lighting_parameters: LightingParameters::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0),
             lighting,
+            lighting_blend: LightingBlend::Overlay,
             distance_color,
             stripe_scale,
         }
@@ -183,20 +393,29 @@ impl DataExport {
                 1
             };
 
-            // self.glitched[pixel.index] = pixel.glitched;
+            self.glitched[pixel.index] = pixel.glitched;
 
-            // if pixel.glitched {
-            //     if self.display_glitches {
-            //         self.set_with_scale::<DATA_TYPE>(pixel.index, [255, 0, 0], new_scale);
-            //     };
-
-            //     continue;
-            // }
+            // Pixels left glitched at the end of the frame are coloured normally unless the glitches are displayed
+            if pixel.glitched && self.display_glitches {
+                self.set_with_scale::<DATA_TYPE>(pixel.index, [255, 0, 0], new_scale);
+                continue;
+            }
 
             self.iterations[pixel.index] = pixel.iteration as u32;
 
+            if DATA_TYPE == 4 {
+                self.atom_domain[pixel.index] = pixel.atom_domain_period as u32;
+            }
+
             if pixel.iteration >= self.maximum_iteration {
-                self.set_with_scale::<DATA_TYPE>(pixel.index, [0, 0, 0], new_scale);
+                self.interior[pixel.index] = match self.interior_coloring {
+                    InteriorColoring::Distance => (pixel.interior_distance / delta_pixel).to_float() as f32,
+                    InteriorColoring::FinalMagnitude => pixel.interior_z.norm() as f32,
+                    InteriorColoring::FinalAngle => pixel.interior_z.arg() as f32,
+                    InteriorColoring::Black => 0.0,
+                };
+
+                self.colour_interior::<DATA_TYPE>(pixel.index, new_scale);
                 continue;
             }
 
@@ -217,9 +436,13 @@ impl DataExport {
                     / 3.0;
             }
 
+            if DATA_TYPE == 5 || DATA_TYPE == 6 {
+                self.average[pixel.index] = pixel.average.average(self.smooth[pixel.index]);
+            }
+
             if DATA_TYPE == 1 || DATA_TYPE == 3 {
                 // This calculates the distance in terms of pixels
-                let temp1 = reference.reference_data_extended[pixel.reference_iteration]
+                let temp1 = reference.reference_extended(pixel.reference_iteration - reference.start_iteration)
                     + pixel.delta_current;
                 let temp2 = temp1.norm();
                 let temp3 = 2.0f64.powi(temp1.exponent - temp2.exponent) / temp2.mantissa;
@@ -255,68 +478,78 @@ impl DataExport {
         }
     }
 
-    pub fn save(&mut self, filename: &str, approximation_order: usize, zoom: &str) {
+    pub fn save(&mut self, filename: &str, approximation_order: usize, zoom: &str) -> crate::Result<()> {
         match self.export_type {
             ExportType::Color => {
-                self.save_colour(filename);
+                self.save_colour(filename)
             }
             ExportType::Raw => {
-                self.save_raw(filename, approximation_order, zoom);
+                self.save_raw(filename, approximation_order, zoom)
             }
             ExportType::Both => {
-                self.save_colour(filename);
-                self.save_raw(filename, approximation_order, zoom);
+                self.save_colour(filename)?;
+                self.save_raw(filename, approximation_order, zoom)
+            }
+            ExportType::Kfb => {
+                self.save_kfb(filename)
             }
-            _ => {}
+            _ => Ok(())
         }
     }
 
-    pub fn save_colour(&mut self, filename: &str) {
+    pub fn save_colour(&mut self, filename: &str) -> crate::Result<()> {
         // Extension is specified
-        if let Some(extension) = filename.split_terminator('.').last() {
-            match extension {
-                "jpg" | "jpeg" | "png" => {
-                    image::save_buffer(
-                        filename.to_owned(),
-                        &self.buffer,
-                        self.image_width as u32,
-                        self.image_height as u32,
-                        image::ColorType::Rgb8,
-                    )
-                    .unwrap();
-
-                    return;
-                }
-                _ => {}
-            }
-        }
+        let path = match filename.split_terminator('.').next_back() {
+            Some("jpg" | "jpeg" | "png") => filename.to_owned(),
+            _ => filename.to_owned() + ".png"
+        };
 
         image::save_buffer(
-            filename.to_owned() + ".png",
+            &path,
             &self.buffer,
             self.image_width as u32,
             self.image_height as u32,
             image::ColorType::Rgb8,
         )
-        .unwrap();
+        .map_err(|error| crate::Error::Write(path, Box::new(error)))
     }
 
-    pub fn save_raw(&mut self, filename: &str, approximation_order: usize, zoom: &str) {
+    pub fn save_raw(&mut self, filename: &str, approximation_order: usize, zoom: &str) -> crate::Result<()> {
         let iterations =
             AnyChannel::new(Text::from("N"), FlatSamples::U32(self.iterations.clone()));
         let smooth = AnyChannel::new(Text::from("NF"), FlatSamples::F32(self.smooth.clone()));
 
-        let channels = if self.data_type == DataType::Distance {
+        let mut channels = if self.data_type == DataType::Distance {
             let distance_x =
                 AnyChannel::new(Text::from("DEX"), FlatSamples::F32(self.distance_x.clone()));
             let distance_y =
                 AnyChannel::new(Text::from("DEY"), FlatSamples::F32(self.distance_y.clone()));
 
             smallvec::smallvec![iterations, smooth, distance_x, distance_y]
+        } else if self.data_type == DataType::AtomDomain {
+            let atom_domain =
+                AnyChannel::new(Text::from("P"), FlatSamples::U32(self.atom_domain.clone()));
+
+            smallvec::smallvec![iterations, smooth, atom_domain]
+        } else if self.data_type == DataType::TriangleInequality || self.data_type == DataType::Curvature {
+            let name = if self.data_type == DataType::TriangleInequality {
+                "TIA"
+            } else {
+                "CA"
+            };
+
+            let average =
+                AnyChannel::new(Text::from(name), FlatSamples::F32(self.average.clone()));
+
+            smallvec::smallvec![iterations, smooth, average]
         } else {
             smallvec::smallvec![iterations, smooth]
         };
 
+        if self.interior_coloring != InteriorColoring::Black {
+            channels.push(AnyChannel::new(Text::from("I"), FlatSamples::F32(self.interior.clone())));
+        }
+
         // let mut layer = simple_image::Layer::new(
         //     simple_image::Text::from("fractal_data").unwrap(),
         //     (self.image_width, self.image_height),
@@ -355,7 +588,83 @@ impl DataExport {
 
         let image = Image::from_layer(layer);
 
-        image.write().to_file(filename.to_owned() + ".exr").unwrap();
+        let path = filename.to_owned() + ".exr";
+
+        image.write().to_file(&path).map_err(|error| crate::Error::Write(path, Box::new(error)))
+    }
+
+    // Writes the Kalles Fraktaler map format, where the per-pixel data is stored in columns
+    pub fn save_kfb(&mut self, filename: &str) -> crate::Result<()> {
+        let number_pixels = self.image_width * self.image_height;
+        let mut output = Vec::with_capacity(23 + 8 * number_pixels + 3 * self.palette_buffer.len());
+
+        output.extend_from_slice(b"KFB");
+        output.extend_from_slice(&(self.image_width as i32).to_le_bytes());
+        output.extend_from_slice(&(self.image_height as i32).to_le_bytes());
+
+        // Pixels that have not been rendered are written as interior, as Kalles Fraktaler has no value for them
+        for i in 0..self.image_width {
+            for j in 0..self.image_height {
+                let iteration = (self.iterations[j * self.image_width + i] as usize).min(self.maximum_iteration);
+                output.extend_from_slice(&(iteration as i32).to_le_bytes());
+            }
+        }
+
+        // Kalles Fraktaler cycles through 1024 palette colours, at a whole number of iterations per colour
+        let iteration_division = (self.palette_iteration_span / 1024.0).round().max(1.0) as i32;
+        output.extend_from_slice(&iteration_division.to_le_bytes());
+
+        // The palette is cyclic in Kalles Fraktaler so the closing colour is not needed
+        let number_colors = if self.palette_buffer.len() > 1 && self.palette_buffer[0] == *self.palette_buffer.last().unwrap() {
+            self.palette_buffer.len() - 1
+        } else {
+            self.palette_buffer.len()
+        };
+
+        output.extend_from_slice(&(number_colors as i32).to_le_bytes());
+
+        for color in &self.palette_buffer[0..number_colors] {
+            let [r, g, b, _] = color.to_rgba8();
+            output.extend_from_slice(&[r, g, b]);
+        }
+
+        output.extend_from_slice(&(self.maximum_iteration as i32).to_le_bytes());
+
+        // The smooth iteration is stored as N + 1 - T, and the interior has no fractional part
+        for i in 0..self.image_width {
+            for j in 0..self.image_height {
+                let k = j * self.image_width + i;
+
+                let trans = if self.iterations[k] as usize >= self.maximum_iteration {
+                    0.0
+                } else {
+                    1.0 - self.smooth[k]
+                };
+
+                output.extend_from_slice(&trans.to_le_bytes());
+            }
+        }
+
+        let path = filename.to_owned() + ".kfb";
+
+        std::fs::write(&path, output).map_err(|error| crate::Error::Write(path, Box::new(error)))
+    }
+
+    pub fn frame_data(&self) -> FrameData {
+        let distance = self.distance_x.iter().zip(&self.distance_y).map(|(x, y)| (x.powi(2) + y.powi(2)).sqrt()).collect();
+
+        FrameData {
+            image_width: self.image_width,
+            image_height: self.image_height,
+            maximum_iteration: self.maximum_iteration,
+            rgb: self.buffer.clone(),
+            iterations: self.iterations.clone(),
+            smooth: self.smooth.clone(),
+            distance,
+            atom_domain: self.atom_domain.clone(),
+            average: self.average.clone(),
+            interior: self.interior.clone(),
+        }
     }
 
     pub fn clear_buffers(&mut self) {
@@ -365,7 +674,10 @@ impl DataExport {
         self.stripe = vec![0.0f32; self.image_width * self.image_height];
         self.distance_x = vec![0.0f32; self.image_width * self.image_height];
         self.distance_y = vec![0.0f32; self.image_width * self.image_height];
-        // self.glitched = vec![false; self.image_width * self.image_height];
+        self.atom_domain = vec![0u32; self.image_width * self.image_height];
+        self.average = vec![0.0f32; self.image_width * self.image_height];
+        self.interior = vec![0.0f32; self.image_width * self.image_height];
+        self.glitched = vec![false; self.image_width * self.image_height];
     }
 
     pub fn regenerate(&mut self) {
@@ -373,19 +685,22 @@ impl DataExport {
             DataType::Distance => self.regenerate_specific::<1>(),
             DataType::Stripe => self.regenerate_specific::<2>(),
             DataType::DistanceStripe => self.regenerate_specific::<3>(),
+            DataType::AtomDomain => self.regenerate_specific::<4>(),
+            DataType::TriangleInequality => self.regenerate_specific::<5>(),
+            DataType::Curvature => self.regenerate_specific::<6>(),
             _ => self.regenerate_specific::<0>(),
         }
     }
 
     pub fn regenerate_specific<const DATA_TYPE: usize>(&mut self) {
         for i in 0..self.iterations.len() {
-            // if self.glitched[i] && self.display_glitches {
-            //     self.set_with_scale::<DATA_TYPE>(i, [255, 0, 0], 1);
-            //     continue;
-            // }
+            if self.glitched[i] && self.display_glitches {
+                self.set_with_scale::<DATA_TYPE>(i, [255, 0, 0], 1);
+                continue;
+            }
 
             if self.iterations[i] >= self.maximum_iteration as u32 {
-                self.set_with_scale::<DATA_TYPE>(i, [0, 0, 0], 1);
+                self.colour_interior::<DATA_TYPE>(i, 1);
                 continue;
             }
 
@@ -451,6 +766,34 @@ impl DataExport {
         )
     }
 
+    #[inline]
+    pub fn calculate_atom_domain_palette_value(&self, k: usize) -> Color {
+        self.calculate_color(
+            self.palette_interpolated_buffer.len() as f32
+                * (self.atom_domain[k] as f32 * GOLDEN_RATIO_CONJUGATE + self.palette_offset).fract(),
+        )
+    }
+
+    // The interior palette is not cyclic, so the value is clamped to the ends of the palette
+    #[inline]
+    pub fn calculate_interior_palette_value(&self, k: usize) -> Color {
+        let value = match self.interior_coloring {
+            InteriorColoring::Distance => (1.0 + self.interior[k].max(0.0)).log2() / INTERIOR_DISTANCE_OCTAVES,
+            InteriorColoring::FinalMagnitude => self.interior[k] / 2.0,
+            InteriorColoring::FinalAngle => self.interior[k] / (2.0 * PI) + 0.5,
+            InteriorColoring::Black => 0.0,
+        };
+
+        let position = value.clamp(0.0, 1.0) * (self.interior_palette_buffer.len() - 1) as f32;
+        let pos1 = position.floor() as usize;
+        let pos2 = (pos1 + 1).min(self.interior_palette_buffer.len() - 1);
+
+        self.interior_palette_buffer[pos1].interpolate_rgb(
+            &self.interior_palette_buffer[pos2],
+            position.fract() as f64,
+        )
+    }
+
     #[inline]
     pub fn calculate_distance_palette_value(&self, distance: f32) -> Color {
         self.calculate_color(
@@ -481,26 +824,47 @@ impl DataExport {
         Color::new(rgb[0], rgb[1], rgb[2], 255.0)
     }
 
+    // A brightness of 0.5 leaves the colour unchanged for the overlay and soft light blends
+    #[inline]
+    pub fn blend_lighting(&self, color: Color, bright: f64) -> Color {
+        let bright = bright.clamp(0.0, 1.0);
+
+        match self.lighting_blend {
+            LightingBlend::Overlay => DataExport::gamma_blend(color, bright),
+            LightingBlend::Multiply => {
+                Color::new(color.r * bright, color.g * bright, color.b * bright, 255.0)
+            }
+            LightingBlend::SoftLight => {
+                let soft_light = |part: f64| (1.0 - 2.0 * bright) * part * part + 2.0 * bright * part;
+
+                Color::new(soft_light(color.r), soft_light(color.g), soft_light(color.b), 255.0)
+            }
+        }
+    }
+
     #[inline]
     pub fn colour_index<const DATA_TYPE: usize>(&mut self, k: usize, scale: usize) {
         let color = match self.coloring_type {
             ColoringType::Distance => {
-                let _bright = self.calculate_blinn_phong(k) as f64;
-
                 let distance = self.calculate_scaled_distance(k);
 
                 // TODO, it could be possible to have some kind of continous distance estimate - which is based on the zoom level as well; so that keyframes can be added together
-                let _color = if self.distance_color {
+                let color = if self.distance_color {
                     self.calculate_distance_palette_value(distance)
                 } else {
                     self.calculate_iteration_palette_value(k)
                 };
 
-                // DataExport::gamma_blend(color, bright)
+                let color = if self.lighting {
+                    self.blend_lighting(color, self.calculate_blinn_phong(k) as f64)
+                } else {
+                    color
+                };
 
-                let out = distance.tanh() as f64;
+                // Fades to black at the boundary, over the distance transition in pixels
+                let fade = distance.tanh() as f64;
 
-                Color::new(out, out, out, 255.0)
+                Color::new(color.r * fade, color.g * fade, color.b * fade, 255.0)
             }
             ColoringType::SmoothIteration | ColoringType::StepIteration => {
                 self.calculate_iteration_palette_value(k)
@@ -537,6 +901,20 @@ impl DataExport {
 
                 let bright = (temp * (1.0 - value) + bright * value) as f64;
 
+                DataExport::gamma_blend(color, bright)
+            }
+            ColoringType::AtomDomain => {
+                self.calculate_atom_domain_palette_value(k)
+            }
+            ColoringType::AtomDomainIteration => {
+                let color = self.calculate_iteration_palette_value(k);
+
+                color.interpolate_rgb(&self.calculate_atom_domain_palette_value(k), ATOM_DOMAIN_TINT)
+            }
+            ColoringType::TriangleInequality | ColoringType::Curvature => {
+                let color = self.calculate_iteration_palette_value(k);
+                let bright = self.average[k] as f64;
+
                 DataExport::gamma_blend(color, bright)
             }
         };
@@ -546,7 +924,26 @@ impl DataExport {
         self.set_with_scale::<DATA_TYPE>(k, [rgba[0], rgba[1], rgba[2]], scale)
     }
 
+    // Interior pixels are black unless an interior colouring is used, or the atom domain period is known
     #[inline]
+    pub fn colour_interior<const DATA_TYPE: usize>(&mut self, k: usize, scale: usize) {
+        let value = if self.interior_coloring != InteriorColoring::Black {
+            let rgba = self.calculate_interior_palette_value(k).to_rgba8();
+
+            [rgba[0], rgba[1], rgba[2]]
+        } else if DATA_TYPE == 4 {
+            let rgba = self.calculate_atom_domain_palette_value(k).to_rgba8();
+
+            [rgba[0], rgba[1], rgba[2]]
+        } else {
+            [0, 0, 0]
+        };
+
+        self.set_with_scale::<DATA_TYPE>(k, value, scale)
+    }
+
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn change_palette(
         &mut self,
         palette: Option<Vec<(u8, u8, u8)>>,
@@ -598,6 +995,7 @@ impl DataExport {
     }
 
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     pub fn change_lighting(
         &mut self,
         direction: f32,
@@ -642,7 +1040,8 @@ impl DataExport {
 
                     self.iterations[scale_index] = self.iterations[index];
                     self.smooth[scale_index] = self.smooth[index];
-                    // self.glitched[scale_index] = self.glitched[index];
+                    self.glitched[scale_index] = self.glitched[index];
+                    self.interior[scale_index] = self.interior[index];
 
                     if DATA_TYPE == 1 || DATA_TYPE == 3 {
                         self.distance_x[scale_index] = self.distance_x[index];
@@ -653,6 +1052,14 @@ impl DataExport {
                         self.stripe[scale_index] = self.stripe[index];
                     }
 
+                    if DATA_TYPE == 4 {
+                        self.atom_domain[scale_index] = self.atom_domain[index];
+                    }
+
+                    if DATA_TYPE == 5 || DATA_TYPE == 6 {
+                        self.average[scale_index] = self.average[index];
+                    }
+
                     self.buffer[3 * (scale_index)] = value[0];
                     self.buffer[3 * (scale_index) + 1] = value[1];
                     self.buffer[3 * (scale_index) + 2] = value[2];
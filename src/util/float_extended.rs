@@ -63,7 +63,7 @@ impl PartialOrd for FloatExtended {
         }
 
         if other.mantissa == 0.0 {
-            return other.mantissa.partial_cmp(&self.mantissa);
+            return self.mantissa.partial_cmp(&other.mantissa);
         }
 
         match self.exponent.cmp(&other.exponent) {
@@ -170,7 +170,7 @@ impl Sub<FloatExtended> for FloatExtended {
             let (new_mantissa, new_exponent) = match self.exponent.cmp(&other.exponent) {
                 Ordering::Equal => (self.mantissa - other.mantissa, self.exponent),
                 Ordering::Greater => (self.mantissa - other.mantissa / 2.0f64.powi(self.exponent - other.exponent), self.exponent),
-                Ordering::Less => (-1.0 * other.mantissa + self.mantissa / 2.0f64.powi(other.exponent - self.exponent), other.exponent)
+                Ordering::Less => (-other.mantissa + self.mantissa / 2.0f64.powi(other.exponent - self.exponent), other.exponent)
             };
 
             FloatExtended::new(new_mantissa, new_exponent)
@@ -240,7 +240,7 @@ impl Sub<f64> for FloatExtended {
             let (new_mantissa, new_exponent) = match self.exponent.cmp(&temp.exponent) {
                 Ordering::Equal => (self.mantissa - temp.mantissa, self.exponent),
                 Ordering::Greater => (self.mantissa - temp.mantissa / 2.0f64.powi(self.exponent - temp.exponent), self.exponent),
-                Ordering::Less => (-1.0 * temp.mantissa + self.mantissa / 2.0f64.powi(temp.exponent - self.exponent), temp.exponent)
+                Ordering::Less => (-temp.mantissa + self.mantissa / 2.0f64.powi(temp.exponent - self.exponent), temp.exponent)
             };
             
             FloatExtended::new(new_mantissa, new_exponent)
@@ -314,7 +314,7 @@ impl Sub<FloatExtended> for f64 {
             let (new_mantissa, new_exponent) = match temp.exponent.cmp(&other.exponent) {
                 Ordering::Equal => (temp.mantissa - other.mantissa, temp.exponent),
                 Ordering::Greater => (temp.mantissa - other.mantissa / 2.0f64.powi(temp.exponent - other.exponent), temp.exponent),
-                Ordering::Less => (-1.0 * other.mantissa + temp.mantissa / 2.0f64.powi(other.exponent - temp.exponent), other.exponent)
+                Ordering::Less => (-other.mantissa + temp.mantissa / 2.0f64.powi(other.exponent - temp.exponent), other.exponent)
             };
 
             FloatExtended::new(new_mantissa, new_exponent)
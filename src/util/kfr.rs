@@ -0,0 +1,150 @@
+use config::{FileStoredFormat, Format, Map, Value};
+use colorgrad::Color;
+
+use std::error::Error;
+use std::fs;
+
+use crate::util::FractalType;
+
+// Kalles Fraktaler interpolates the palette keys over this many colours
+const KFR_PALETTE_LENGTH: f64 = 1024.0;
+
+// Allows Kalles Fraktaler location files to be used as a config source, with File::new(path, KfrFormat)
+#[derive(Clone, Debug)]
+pub struct KfrFormat;
+
+impl Format for KfrFormat {
+    fn parse(&self, uri: Option<&String>, text: &str) -> Result<Map<String, Value>, Box<dyn Error + Send + Sync>> {
+        let mut settings = Map::new();
+
+        // Kept to check that the power is supported once the whole file has been read
+        let mut formula = 0;
+        let mut power = 2;
+
+        for line in text.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue
+            };
+
+            let invalid = |error: &dyn Error| format!("invalid KFR value for {}: {}", key, error);
+
+            let (setting, setting_value) = match key {
+                "Re" => ("real", Value::new(uri, value.to_owned())),
+                "Im" => ("imag", Value::new(uri, value.to_owned())),
+                "Zoom" => {
+                    // The renderer needs the zoom in mantissa-exponent form
+                    let zoom = if value.contains(&['E', 'e'][..]) {
+                        value.to_owned()
+                    } else {
+                        format!("{:E}", value.parse::<f64>().map_err(|e| invalid(&e))?)
+                    };
+
+                    ("zoom", Value::new(uri, zoom))
+                },
+                "Iterations" => ("iterations", Value::new(uri, value.parse::<i64>().map_err(|e| invalid(&e))?)),
+                "Rotate" => ("rotate", Value::new(uri, value.parse::<f64>().map_err(|e| invalid(&e))?)),
+                "ColorOffset" => ("palette_offset", Value::new(uri, value.parse::<f64>().map_err(|e| invalid(&e))? / KFR_PALETTE_LENGTH)),
+                "IterDiv" => ("palette_iteration_span", Value::new(uri, value.parse::<f64>().map_err(|e| invalid(&e))? * KFR_PALETTE_LENGTH)),
+                "Colors" => {
+                    let colors = value.split(',')
+                        .map(|component| component.trim())
+                        .filter(|component| !component.is_empty())
+                        .map(|component| component.parse::<i64>().map(|component| Value::new(uri, component)))
+                        .collect::<Result<Vec<Value>, _>>()
+                        .map_err(|e| invalid(&e))?;
+
+                    ("palette", Value::new(uri, colors))
+                },
+                "Power" => {
+                    power = value.parse::<i64>().map_err(|e| invalid(&e))?;
+
+                    ("fractal_power", Value::new(uri, power))
+                },
+                "FractalType" => {
+                    formula = value.parse::<i64>().map_err(|e| invalid(&e))?;
+
+                    // Reading the other formulas as the mandelbrot would render a different fractal
+                    let fractal_type = match formula {
+                        0 => "mandelbrot",
+                        1 => "burningship",
+                        2 => "buffalo",
+                        3 => "celtic",
+                        4 => "tricorn",
+                        7 => "perpendicular",
+                        _ => return Err(format!("unsupported KFR fractal type {}", formula).into())
+                    };
+
+                    ("fractal_type", Value::new(uri, fractal_type.to_owned()))
+                },
+                _ => continue
+            };
+
+            settings.insert(setting.to_owned(), setting_value);
+        }
+
+        // Only the power 2 abs variations are implemented
+        if formula != 0 && power != 2 {
+            return Err(format!("unsupported KFR power {} for fractal type {}", power, formula).into());
+        }
+
+        Ok(settings)
+    }
+}
+
+impl FileStoredFormat for KfrFormat {
+    fn file_extensions(&self) -> &'static [&'static str] {
+        &["kfr"]
+    }
+}
+
+// The location and colouring of a frame in the form written to Kalles Fraktaler location files
+pub struct KfrLocation<'a> {
+    pub real: String,
+    pub imag: String,
+    pub zoom: String,
+    pub iterations: usize,
+    // Rotation in degrees
+    pub rotate: f64,
+    pub fractal_type: FractalType,
+    pub palette: &'a [Color],
+    pub palette_offset: f32,
+    pub palette_iteration_span: f32,
+}
+
+impl KfrLocation<'_> {
+    pub fn save(&self, filename: &str) -> Result<(), crate::Error> {
+        let path = filename.to_owned() + ".kfr";
+
+        fs::write(&path, self.generate()).map_err(|error| crate::Error::Write(path, Box::new(error)))
+    }
+
+    pub fn generate(&self) -> String {
+        let (fractal_type, power) = match self.fractal_type {
+            FractalType::Mandelbrot(power) => (0, power),
+            FractalType::BurningShip(_) => (1, 2),
+            FractalType::Buffalo(_) => (2, 2),
+            FractalType::Celtic(_) => (3, 2),
+            FractalType::Tricorn(_) => (4, 2),
+            FractalType::PerpendicularBurningShip(_) => (7, 2),
+        };
+
+        // The palette is cyclic in Kalles Fraktaler so the closing colour is not needed
+        let number_colors = if self.palette.len() > 1 && self.palette[0] == *self.palette.last().unwrap() {
+            self.palette.len() - 1
+        } else {
+            self.palette.len()
+        };
+
+        let colors = self.palette[0..number_colors].iter().map(|color| {
+            let [r, g, b, _] = color.to_rgba8();
+            format!("{},{},{},", r, g, b)
+        }).collect::<String>();
+
+        let color_offset = (self.palette_offset.rem_euclid(1.0) as f64 * KFR_PALETTE_LENGTH).round() as usize % KFR_PALETTE_LENGTH as usize;
+        let iteration_division = self.palette_iteration_span as f64 / KFR_PALETTE_LENGTH;
+
+        format!("Re: {}\r\nIm: {}\r\nZoom: {}\r\nIterations: {}\r\nIterDiv: {:.6}\r\nColorOffset: {}\r\nRotate: {:.6}\r\nColors: {}\r\nSmooth: 1\r\nPower: {}\r\nFractalType: {}\r\n",
+            self.real, self.imag, self.zoom, self.iterations, iteration_division, color_offset, self.rotate, colors, power, fractal_type)
+    }
+}
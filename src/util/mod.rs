@@ -5,13 +5,19 @@ pub mod float_extended;
 pub mod complex_extended;
 pub mod recolour_exr;
 pub mod progress;
+pub mod kfr;
+pub mod render_handle;
+pub mod navigation;
 
 use colorgrad::{CustomGradient, Interpolation, Color, BlendMode};
 pub use complex_extended::ComplexExtended;
-use config::Config;
+use crate::Error;
 pub use float_extended::FloatExtended;
 pub use recolour_exr::RecolourExr;
-pub use progress::ProgressCounters;
+pub use progress::{FrameStatistics, JsonProgress, ProgressCounters, ProgressObserver, RenderStage, StageMonitor, StageProgress, TerminalProgress};
+pub use kfr::{KfrFormat, KfrLocation};
+pub use render_handle::RenderHandle;
+pub use navigation::{MinibrotLocation, MisiurewiczLocation, MisiurewiczPoint};
 
 pub type ComplexFixed<T> = num_complex::Complex<T>;
 pub type ComplexArbitrary = rug::Complex;
@@ -19,8 +25,6 @@ pub type FloatArbitrary = rug::Float;
 
 use std::os::raw::{c_double, c_int};
 
-use self::data_export::{ColoringType, DataType};
-
 extern "C" {
     fn frexp(x: c_double, exp: *mut c_int) -> c_double;
     fn ldexp(x: c_double, exp: c_int) -> c_double;
@@ -68,21 +72,26 @@ pub fn to_extended(value: &ComplexArbitrary) -> ComplexExtended {
     ComplexExtended::new2(re, im, exponent)
 }
 
-pub fn string_to_extended(string: &str) -> FloatExtended {
-    let temp: Vec<&str> = string.split(&['E', 'e'][..]).collect();
+pub fn string_to_extended(string: &str) -> Result<FloatExtended, Error> {
+    // Strings without an exponent are read as plain numbers
+    let (first, second) = string.split_once(&['E', 'e'][..]).unwrap_or((string, ""));
 
-    let first = temp[0].parse::<f64>().unwrap();
+    let first = first.trim().parse::<f64>().map_err(|_| Error::InvalidZoom(string.to_owned()))?;
 
-    let second = if temp[1].is_empty() {
+    let second = if second.is_empty() {
         0.0
     } else {
-        temp[1].parse::<f64>().unwrap() * LOG2_10
+        second.trim().parse::<f64>().map_err(|_| Error::InvalidZoom(string.to_owned()))? * LOG2_10
     };
 
+    if !first.is_finite() || !second.is_finite() {
+        return Err(Error::InvalidZoom(string.to_owned()));
+    }
+
     if second < 0.0 {
-        FloatExtended::new(first * 2.0f64.powf(1.0 + second.fract()), second.floor() as i32)
+        Ok(FloatExtended::new(first * 2.0f64.powf(1.0 + second.fract()), second.floor() as i32))
     } else {
-        FloatExtended::new(first * 2.0f64.powf(second.fract()), second.floor() as i32)
+        Ok(FloatExtended::new(first * 2.0f64.powf(second.fract()), second.floor() as i32))
     }
 }
 
@@ -138,10 +147,20 @@ pub fn diff_abs(a: f64, b: f64) -> f64 {
     }
 }
 
+#[inline]
+pub fn diff_abs_extended(a: FloatExtended, b: FloatExtended) -> FloatExtended {
+    match (a.mantissa >= 0.0, (a + b).mantissa >= 0.0) {
+        (true, true) => b,
+        (true, _) => -2.0 * a - b,
+        (_, true) => 2.0 * a + b,
+        (_, _) => -1.0 * b
+    }
+}
+
 pub fn get_delta_top_left(delta_pixel: f64, image_width: usize, image_height: usize, cos_rotate: f64, sin_rotate: f64) -> ComplexFixed<f64> {
     let aspect = image_width as f64 / image_height as f64;
 
-    let temp_real = -0.5 * (image_height - 1) as f64 * delta_pixel * aspect as f64;
+    let temp_real = -0.5 * (image_height - 1) as f64 * delta_pixel * aspect;
     let temp_imag = -0.5 * (image_height - 1) as f64 * delta_pixel;
 
     ComplexFixed::new(
@@ -174,34 +193,104 @@ pub fn generate_default_palette() -> (Vec<Color>, Vec<Color>) {
     (palette_generator.colors(6), palette_generator.colors(6 * 64))
 }
 
-pub fn get_fractal_type_from_settings(settings: &Config) -> FractalType {
-    let fractal_power = settings.get_int("fractal_power").unwrap_or(2) as usize;
+// Interpolated palette for the interior, from the dark boundary to the lighter centre of the components
+pub fn generate_default_interior_palette() -> Vec<Color> {
+    let palette_generator = CustomGradient::new()
+        .colors(
+            &[Color::from_rgba8(0, 0, 0, 255),
+            Color::from_rgba8(16, 24, 64, 255),
+            Color::from_rgba8(72, 104, 168, 255),
+            Color::from_rgba8(224, 232, 255, 255)])
+            .interpolation(Interpolation::CatmullRom).mode(BlendMode::Oklab)
+            .build().unwrap();
+
+    palette_generator.colors(4 * 64)
+}
+
+// Running sum of the terms of an average colouring, where the last term is kept so that the average can be interpolated
+// between the last two iterations with the smooth iteration
+#[derive(Clone, Copy)]
+pub struct AverageSum {
+    pub sum: f64,
+    pub last: f64,
+    pub count: usize,
+    // The last two values of z, which the terms are calculated from
+    pub previous: [ComplexFixed<f64>; 2],
+}
 
-    match settings.get("fractal_type").unwrap_or_else(|_| String::from("mandelbrot")).to_ascii_uppercase().as_ref() {
-        "MANDELBROT" => FractalType::Mandelbrot(fractal_power),
-        "BURNINGSHIP" => FractalType::BurningShip(fractal_power),
-        _ => FractalType::Mandelbrot(fractal_power)
+impl AverageSum {
+    pub fn new() -> Self {
+        AverageSum {
+            sum: 0.0,
+            last: 0.0,
+            count: 0,
+            previous: [ComplexFixed::new(0.0, 0.0); 2],
+        }
     }
-}
 
-pub fn get_data_coloring_type_from_settings(settings: &Config) -> (ColoringType, DataType) {
-    let coloring_type = match settings.get("coloring_type").unwrap_or_else(|_| String::from("smooth_iteration")).to_ascii_uppercase().as_ref() {
-        "SMOOTH_ITERATION" | "SMOOTH" => ColoringType::SmoothIteration,
-        "STEP_ITERATION" | "STEP" => ColoringType::StepIteration,
-        "DISTANCE" => ColoringType::Distance,
-        "STRIPE" => ColoringType::Stripe,
-        "DISTANCE_STRIPE" => ColoringType::DistanceStripe,
-        _ => ColoringType::SmoothIteration
-    };
+    #[inline]
+    fn add_term(&mut self, term: f64, z: ComplexFixed<f64>) {
+        if term.is_finite() {
+            self.sum += term;
+            self.last = term;
+            self.count += 1;
+        }
 
-    let data_type = match coloring_type {
-        ColoringType::SmoothIteration | ColoringType::StepIteration => DataType::Iteration,
-        ColoringType::Stripe => DataType::Stripe,
-        ColoringType::DistanceStripe => DataType::DistanceStripe,
-        _ => DataType::Distance
-    };
+        self.previous = [z, self.previous[0]];
+    }
+
+    // Where |z| is between the bounds given by the triangle inequality, |z_n^p| - |c| and |z_n^p| + |c|
+    #[inline]
+    pub fn add_triangle_inequality(&mut self, z: ComplexFixed<f64>, c_norm: f64, fractal_power: i32) {
+        let previous_norm = self.previous[0].norm().powi(fractal_power);
+
+        let lower = (previous_norm - c_norm).abs();
+        let upper = previous_norm + c_norm;
+
+        // The first iteration has both bounds equal to |c|
+        let term = if upper > lower {
+            (z.norm() - lower) / (upper - lower)
+        } else {
+            f64::NAN
+        };
+
+        self.add_term(term, z);
+    }
+
+    // The angle between the last two steps of the orbit, scaled to the range 0 to 1
+    #[inline]
+    pub fn add_curvature(&mut self, z: ComplexFixed<f64>) {
+        let step = z - self.previous[0];
+        let previous_step = self.previous[0] - self.previous[1];
+
+        let term = if previous_step.norm_sqr() > 0.0 {
+            (step / previous_step).arg().abs() * std::f64::consts::FRAC_1_PI
+        } else {
+            f64::NAN
+        };
+
+        self.add_term(term, z);
+    }
 
-    (coloring_type, data_type)
+    // The average without the last term is weighted by the remaining fraction of the smooth iteration
+    pub fn average(&self, smooth: f32) -> f32 {
+        match self.count {
+            0 => 0.0,
+            1 => self.sum as f32,
+            _ => {
+                let average = self.sum / self.count as f64;
+                let previous_average = (self.sum - self.last) / (self.count - 1) as f64;
+
+                (average * smooth as f64 + previous_average * (1.0 - smooth as f64)) as f32
+            }
+        }
+    }
+}
+
+impl Default for AverageSum {
+    fn default() -> Self {
+        AverageSum::new()
+    }
 }
 
 #[derive(Clone)]
@@ -215,10 +304,47 @@ pub struct PixelData {
     pub z_norm: f64,
     pub stripe_storage: [ComplexFixed<f64>; 4],
     pub stripe_iteration: usize,
+    // The smallest |z|^2 of the orbit and the iteration it was reached at, which is the atom domain period
+    pub atom_domain_norm: f64,
+    pub atom_domain_period: usize,
+    // Derivative of z over the current period, which is small once the orbit is on an attracting cycle
+    pub derivative_z: ComplexExtended,
+    // The final z and interior distance estimate for pixels inside the set
+    pub interior_z: ComplexFixed<f64>,
+    pub interior_distance: FloatExtended,
+    // Period of the next cycle to check, which is a multiple of the atom domain period or zero for the atom domain period itself
+    pub interior_period: usize,
+    // The triangle inequality or curvature average of the orbit
+    pub average: AverageSum,
+    pub glitched: bool,
+    // Set once the pixel has been through the first iteration pass, so that a cancelled frame can be resumed
+    pub iterated: bool,
 }
 
-#[derive(Copy, Clone, PartialEq)]
+// The highest mandelbrot power that can be dispatched to at runtime
+pub const MAXIMUM_FRACTAL_POWER: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum FractalType {
     Mandelbrot(usize),
-    BurningShip(usize)
+    BurningShip(usize),
+    Tricorn(usize),
+    Celtic(usize),
+    Buffalo(usize),
+    PerpendicularBurningShip(usize)
+}
+
+impl FractalType {
+    pub fn power(&self) -> usize {
+        match self {
+            FractalType::Mandelbrot(power) => *power,
+            // Only the power 2 abs variations are currently implemented
+            _ => 2
+        }
+    }
+
+    // Only the mandelbrot can be written as a polynomial in the complex delta
+    pub fn holomorphic(&self) -> bool {
+        matches!(self, FractalType::Mandelbrot(_))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,74 @@
+use std::fs;
+
+use crate::util::{ComplexArbitrary, ComplexExtended, FloatExtended, extended_to_string_long};
+
+// Digits of the centre that are written beyond the size of a pixel
+const EXTRA_DIGITS: usize = 20;
+
+// The nucleus of a minibrot, with the zoom and rotation that fit the minibrot to the image
+pub struct MinibrotLocation {
+    pub nucleus: ComplexArbitrary,
+    pub period: usize,
+    pub zoom: FloatExtended,
+    pub iterations: usize,
+    // Rotation in degrees
+    pub rotate: f64,
+}
+
+impl MinibrotLocation {
+    pub fn save(&self, path: &str) -> Result<(), crate::Error> {
+        save_location(path, &self.generate())
+    }
+
+    pub fn generate(&self) -> String {
+        generate_location(&self.nucleus, self.zoom, self.iterations, self.rotate)
+    }
+}
+
+// A Misiurewicz point, where the orbit lands on a repelling cycle after the preperiod
+pub struct MisiurewiczPoint {
+    pub point: ComplexArbitrary,
+    pub preperiod: usize,
+    pub period: usize,
+    // Size of the features around the point, from the derivative of the orbit when it lands on the cycle
+    pub size: FloatExtended,
+    // Scaling and rotation between each repetition of the features towards the point
+    pub multiplier: ComplexExtended,
+}
+
+// A Misiurewicz point, with the zoom and rotation that fit its features to the image
+pub struct MisiurewiczLocation {
+    pub misiurewicz: MisiurewiczPoint,
+    pub zoom: FloatExtended,
+    pub iterations: usize,
+    // Rotation in degrees
+    pub rotate: f64,
+}
+
+impl MisiurewiczLocation {
+    pub fn save(&self, path: &str) -> Result<(), crate::Error> {
+        save_location(path, &self.generate())
+    }
+
+    pub fn generate(&self) -> String {
+        generate_location(&self.misiurewicz.point, self.zoom, self.iterations, self.rotate)
+    }
+}
+
+fn save_location(path: &str, location: &str) -> Result<(), crate::Error> {
+    fs::write(path, location).map_err(|error| crate::Error::Write(path.to_owned(), Box::new(error)))
+}
+
+// Generates a location file in the same form as the files in ./locations
+fn generate_location(centre: &ComplexArbitrary, zoom: FloatExtended, iterations: usize, rotate: f64) -> String {
+    let digits = (zoom.exponent.max(0) as f64 * std::f64::consts::LOG10_2) as usize + EXTRA_DIGITS;
+
+    format!("real = \"{:.*}\"\nimag = \"{:.*}\"\nzoom = \"{}\"\niterations = {}\nrotate = {:.6}\n",
+        digits,
+        centre.real(),
+        digits,
+        centre.imag(),
+        extended_to_string_long(zoom),
+        iterations,
+        rotate)
+}
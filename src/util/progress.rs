@@ -1,4 +1,9 @@
-use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+use std::io::Write;
+use std::sync::{Arc, mpsc, atomic::{AtomicUsize, Ordering}};
+use std::thread;
+use std::time::Duration;
+
+use parking_lot::Mutex;
 
 pub struct ProgressCounters {
     pub reference: Arc<AtomicUsize>,
@@ -55,4 +60,238 @@ impl ProgressCounters {
         self.reference_maximum.store(maximum_iteration - 1, Ordering::SeqCst);
         self.reference_count.store(1, Ordering::SeqCst);
     }
-}
\ No newline at end of file
+
+    // Reports the progress of the stage to the observer from a separate thread until the monitor is finished or dropped
+    // The pixel offset and count are used to find the iteration progress, as the counter is not reset between stages
+    pub fn monitor(&self, stage: RenderStage, observer: Arc<dyn ProgressObserver>, pixel_offset: usize, pixel_count: usize) -> StageMonitor {
+        let reference = self.reference.clone();
+        let reference_maximum = self.reference_maximum.clone();
+        let series_approximation = self.series_approximation.clone();
+        let series_validation = self.series_validation.clone();
+        let iteration = self.iteration.clone();
+
+        let (sender, receiver) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = receiver.recv_timeout(Duration::from_millis(100)) {
+                let reference_maximum = reference_maximum.load(Ordering::Relaxed).max(1) as f64;
+
+                let progress = StageProgress {
+                    reference: reference.load(Ordering::Relaxed) as f64 / reference_maximum,
+                    series_approximation: series_approximation.load(Ordering::Relaxed) as f64 / reference_maximum,
+                    // The probes are checked in two passes
+                    probe_validation: series_validation.load(Ordering::Relaxed) as f64 / 2.0,
+                    iteration: (iteration.load(Ordering::Relaxed) as f64 - pixel_offset as f64) / pixel_count.max(1) as f64,
+                };
+
+                observer.stage_progress(stage, &progress);
+            }
+        });
+
+        StageMonitor {
+            sender,
+            handle,
+        }
+    }
+}
+
+pub struct StageMonitor {
+    sender: mpsc::Sender<()>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl StageMonitor {
+    // Stops the reporting, waiting so that no progress is reported after the stage is finished
+    pub fn finish(self) {
+        let _ = self.sender.send(());
+        let _ = self.handle.join();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderStage {
+    // Reference orbit, series approximation and probe checking
+    Approximation,
+    Iteration,
+    // Glitch correction with additional references
+    Correction,
+}
+
+impl RenderStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderStage::Approximation => "approximation",
+            RenderStage::Iteration => "iteration",
+            RenderStage::Correction => "correction",
+        }
+    }
+}
+
+// Fractions from 0 to 1 of the work done in each part of the stage
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageProgress {
+    pub reference: f64,
+    pub series_approximation: f64,
+    pub probe_validation: f64,
+    pub iteration: f64,
+}
+
+impl StageProgress {
+    pub fn percentage(&self, stage: RenderStage) -> f64 {
+        match stage {
+            // 45% weighting to first reference, 45% to SA calculation, 10% to SA checking
+            RenderStage::Approximation => 45.0 * self.reference + 45.0 * self.series_approximation + 10.0 * self.probe_validation,
+            _ => 100.0 * self.iteration
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStatistics {
+    pub skipped_iterations: usize,
+    pub approximation_order: usize,
+    pub maximum_iteration: usize,
+    pub reference_count: usize,
+    // Bytes used by the center reference
+    pub reference_memory: usize,
+}
+
+// Receives the progress of a render. Progress is reported from a separate thread, so this needs to be thread safe
+pub trait ProgressObserver: Send + Sync {
+    fn render_started(&self) {}
+    fn frame_started(&self, _frame_index: usize, _zoom: &str) {}
+    fn stage_started(&self, _stage: RenderStage) {}
+    fn stage_progress(&self, _stage: RenderStage, _progress: &StageProgress) {}
+    fn stage_finished(&self, _stage: RenderStage, _elapsed: Duration, _statistics: &FrameStatistics) {}
+    fn frame_finished(&self, _frame_index: usize, _elapsed: Duration) {}
+    fn frame_stopped(&self) {}
+    // Something that did not stop the render went wrong, such as a saved reference that could not be read
+    fn warning(&self, _message: &str) {}
+}
+
+// Prints a table with a row for each frame, updating the percentage of the current stage in place
+pub struct TerminalProgress;
+
+impl TerminalProgress {
+    // As for JsonProgress, a closed stdout does not stop the render
+    fn print(text: String) {
+        let mut stdout = std::io::stdout();
+
+        let _ = write!(stdout, "{}", text);
+        let _ = stdout.flush();
+    }
+}
+
+impl ProgressObserver for TerminalProgress {
+    fn render_started(&self) {
+        TerminalProgress::print(format!(" {:<15}| {:<15}| {:<15}| {:<6}| {:<15}| {:<10}| {:<15}| {:<15}| {:<6}| {:<15}\n", "Zoom", "Approx [ms]", "Skipped [it]", "Order", "Maximum [it]", "Ref [MB]", "Iteration [ms]", "Correct [ms]", "Ref", "Frame [ms]"));
+    }
+
+    fn frame_started(&self, _frame_index: usize, zoom: &str) {
+        TerminalProgress::print(format!(" {:<15}", zoom));
+    }
+
+    fn stage_started(&self, _stage: RenderStage) {
+        TerminalProgress::print(String::from("|               "));
+    }
+
+    fn stage_progress(&self, stage: RenderStage, progress: &StageProgress) {
+        TerminalProgress::print(format!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:^14}", format!("{:.2}%", progress.percentage(stage))));
+    }
+
+    fn stage_finished(&self, stage: RenderStage, elapsed: Duration, statistics: &FrameStatistics) {
+        let columns = match stage {
+            RenderStage::Approximation => format!("| {:<15}| {:<6}| {:<15}| {:<10.1}", statistics.skipped_iterations, statistics.approximation_order, statistics.maximum_iteration, statistics.reference_memory as f64 / 1048576.0),
+            RenderStage::Iteration => String::new(),
+            RenderStage::Correction => format!("| {:<6}", statistics.reference_count),
+        };
+
+        TerminalProgress::print(format!("\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08\x08{:<15}{}", elapsed.as_millis(), columns));
+    }
+
+    fn frame_finished(&self, _frame_index: usize, elapsed: Duration) {
+        TerminalProgress::print(format!("| {:<15}\n", elapsed.as_millis()));
+    }
+
+    fn frame_stopped(&self) {
+        TerminalProgress::print(String::from("\n"));
+    }
+
+    fn warning(&self, message: &str) {
+        eprintln!("warning: {}", message);
+    }
+}
+
+// Writes each update as a line of JSON, for reading the progress from another program
+pub struct JsonProgress {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonProgress {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        JsonProgress {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    // The messages can contain paths and the text of other errors, which may need escaping in a JSON string
+    fn escape(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+
+        for character in text.chars() {
+            match character {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                character if character.is_control() => escaped.push_str(&format!("\\u{:04x}", character as u32)),
+                character => escaped.push(character)
+            }
+        }
+
+        escaped
+    }
+
+    fn write(&self, line: String) {
+        let mut writer = self.writer.lock();
+
+        // Progress is not important enough to stop the render if it can not be written
+        let _ = writeln!(writer, "{}", line);
+        let _ = writer.flush();
+    }
+}
+
+impl ProgressObserver for JsonProgress {
+    fn render_started(&self) {
+        self.write(String::from("{\"event\":\"render_started\"}"));
+    }
+
+    fn frame_started(&self, frame_index: usize, zoom: &str) {
+        self.write(format!("{{\"event\":\"frame_started\",\"frame\":{},\"zoom\":\"{}\"}}", frame_index, zoom));
+    }
+
+    fn stage_started(&self, stage: RenderStage) {
+        self.write(format!("{{\"event\":\"stage_started\",\"stage\":\"{}\"}}", stage.name()));
+    }
+
+    fn stage_progress(&self, stage: RenderStage, progress: &StageProgress) {
+        self.write(format!("{{\"event\":\"stage_progress\",\"stage\":\"{}\",\"percentage\":{:.2},\"reference\":{:.4},\"series_approximation\":{:.4},\"probe_validation\":{:.4},\"iteration\":{:.4}}}",
+            stage.name(), progress.percentage(stage), progress.reference, progress.series_approximation, progress.probe_validation, progress.iteration));
+    }
+
+    fn stage_finished(&self, stage: RenderStage, elapsed: Duration, statistics: &FrameStatistics) {
+        self.write(format!("{{\"event\":\"stage_finished\",\"stage\":\"{}\",\"time_ms\":{},\"skipped_iterations\":{},\"approximation_order\":{},\"maximum_iteration\":{},\"reference_count\":{},\"reference_memory\":{}}}",
+            stage.name(), elapsed.as_millis(), statistics.skipped_iterations, statistics.approximation_order, statistics.maximum_iteration, statistics.reference_count, statistics.reference_memory));
+    }
+
+    fn frame_finished(&self, frame_index: usize, elapsed: Duration) {
+        self.write(format!("{{\"event\":\"frame_finished\",\"frame\":{},\"time_ms\":{}}}", frame_index, elapsed.as_millis()));
+    }
+
+    fn frame_stopped(&self) {
+        self.write(String::from("{\"event\":\"frame_stopped\"}"));
+    }
+
+    fn warning(&self, message: &str) {
+        self.write(format!("{{\"event\":\"warning\",\"message\":\"{}\"}}", JsonProgress::escape(message)));
+    }
+}
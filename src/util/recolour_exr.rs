@@ -5,9 +5,9 @@ use config::Config;
 use colorgrad::{Color, CustomGradient, Interpolation, BlendMode};
 
 use std::fs;
-use std::time::Instant;
 
 use crate::util::generate_default_palette;
+use crate::util::data_export::DataExport;
 
 pub struct RecolourExr {
     palette_buffer: Vec<Color>,
@@ -17,16 +17,23 @@ pub struct RecolourExr {
 }
 
 impl RecolourExr {
-    pub fn new(settings: Config) -> Self {
+    pub fn new(settings: Config) -> crate::Result<Self> {
         let (_, palette_buffer) = if let Ok(colour_values) = settings.get_array("palette") {
+            if colour_values.is_empty() || colour_values.len() % 3 != 0 {
+                return Err(crate::Error::InvalidPalette(format!("expected red, green and blue values for each colour, found {} values", colour_values.len())));
+            }
+
             let mut colors = colour_values.chunks_exact(3).map(|value| {
-                Color::from_rgba8(value[0].clone().into_int().unwrap() as u8, 
-                    value[1].clone().into_int().unwrap() as u8, 
-                    value[2].clone().into_int().unwrap() as u8,
-                    255)
-            }).collect::<Vec<Color>>();
+                let mut rgb = [0u8; 3];
+
+                for (channel, value) in rgb.iter_mut().zip(value) {
+                    *channel = value.clone().into_int().map_err(|error| crate::Error::InvalidPalette(error.to_string()))? as u8;
+                }
 
-            if colors[0] != *colors.last().unwrap() {
+                Ok(Color::from_rgba8(rgb[0], rgb[1], rgb[2], 255))
+            }).collect::<crate::Result<Vec<Color>>>()?;
+
+            if colors[0] != colors[colors.len() - 1] {
                 colors.push(colors[0].clone());
             };
 
@@ -40,7 +47,8 @@ impl RecolourExr {
                 .colors(&colors[0..number_colors])
                 .interpolation(Interpolation::CatmullRom)
                 .mode(BlendMode::Oklab)
-                .build().unwrap();
+                .build()
+                .map_err(|error| crate::Error::InvalidPalette(error.to_string()))?;
 
             (colors, palette_generator.colors(number_colors * 64))
         } else {
@@ -50,61 +58,71 @@ impl RecolourExr {
         let palette_iteration_span = settings.get_float("palette_iteration_span").unwrap_or(10.0) as f32;
         let palette_offset = settings.get_float("iteration_offset").unwrap_or(0.0) as f32;
 
-        let paths = fs::read_dir("./output/").unwrap();
+        let output_directory = settings.get_string("output_directory").unwrap_or_else(|_| String::from("output"));
+
+        let paths = fs::read_dir(&output_directory)
+            .map_err(|error| crate::Error::Read(output_directory.clone(), Box::new(error)))?;
         let mut exr_files = Vec::new();
     
         for path in paths {
-            let name = path.unwrap()
+            let name = path.map_err(|error| crate::Error::Read(output_directory.clone(), Box::new(error)))?
                 .path()
-                .to_owned()
-                .to_str()
-                .unwrap()
-                .to_string();
+                .to_string_lossy()
+                .into_owned();
             
             if name.contains(".exr") {
                 exr_files.push(name)
             }
         };
 
-        RecolourExr {
+        Ok(RecolourExr {
             palette_buffer,
             files: exr_files,
             palette_iteration_span,
             palette_offset
-        }
+        })
     }
 
-    pub fn colour(&self) {
-        let colouring_time = Instant::now();
-
+    // Writes a png next to each exr file, and returns the number of images that were recoloured
+    pub fn colour(&self) -> crate::Result<usize> {
         (&self.files).into_par_iter()
-        .for_each(|exr_file| {
-            let raw_data = read_all_data_from_file(&exr_file).unwrap();
+        .try_for_each(|exr_file| {
+            let raw_data = read_all_data_from_file(exr_file)
+                .map_err(|error| crate::Error::Read(exr_file.clone(), Box::new(error)))?;
 
             let mut iterations = Vec::new();
             let mut smooth = Vec::new();
+            let mut average = Vec::new();
 
             for layer in &raw_data.layer_data {
                 for channel in &layer.channel_data.list {
+                    // Other channels such as the distance estimate and atom domain are not used
+                    if !channel.name.eq("N") && !channel.name.eq("NF") && !channel.name.eq("TIA") && !channel.name.eq("CA") {
+                        continue;
+                    }
+
                     match &channel.sample_data{
                     Levels::Singular(samples) => {
                         match samples {
                             FlatSamples::F16(f16_vec) => {
-                                smooth = f16_vec.clone();
+                                smooth = f16_vec.iter().map(|value| value.to_f32()).collect();
+                            },
+                            FlatSamples::F32(f32_vec) => {
+                                // The triangle inequality and curvature averages brighten the colour like the stripe average
+                                if channel.name.eq("NF") {
+                                    smooth = f32_vec.clone();
+                                } else {
+                                    average = f32_vec.clone();
+                                }
                             },
-                            FlatSamples::F32(_) => {},
                             FlatSamples::U32(u32_vec) => {
                                 iterations = u32_vec.clone();
                             }
                         };
                     },
-                    Levels::Mip { level_data, .. } => {
-                        // Handle Mip levels if necessary
-                        println!("Mip levels: {:?}", level_data);
-                    },
-                    Levels::Rip { level_data, .. } => {
-                        // Handle Rip levels if necessary
-                        println!("Rip levels: {:?}", level_data);
+                    // The raw export only writes a single level
+                    Levels::Mip { .. } | Levels::Rip { .. } => {
+                        return Err(crate::Error::Read(exr_file.clone(), format!("channel {} has more than one level", channel.name).into()));
                     },
                 }
                 }
@@ -113,8 +131,6 @@ impl RecolourExr {
             let file_name = exr_file.split(".exr").collect::<Vec<_>>()[0];
             let dimensions = raw_data.attributes.display_window.size;
 
-            println!("{}.exr", file_name);
-
             let mut rgb_buffer = vec![0u8; iterations.len() * 3];
             
             for i in 0..iterations.len() {
@@ -123,7 +139,7 @@ impl RecolourExr {
                     rgb_buffer[3 * i + 1] = 0u8;
                     rgb_buffer[3 * i + 2] = 0u8;
                 } else {
-                    let temp = self.palette_buffer.len() as f32 * ((iterations[i] as f32 + smooth[i].to_f32()) / self.palette_iteration_span + self.palette_offset).fract();
+                    let temp = self.palette_buffer.len() as f32 * ((iterations[i] as f32 + smooth[i]) / self.palette_iteration_span + self.palette_offset).fract();
 
                     let pos1 = temp.floor() as usize;
                     let pos2 = if pos1 == (self.palette_buffer.len() - 1) {
@@ -134,7 +150,13 @@ impl RecolourExr {
 
                     let frac = temp.fract() as f64;
 
-                    let (r, g, b, _) = self.palette_buffer[pos1].interpolate_rgb(&self.palette_buffer[pos2], frac).to_linear_rgba_u8();
+                    let mut colour = self.palette_buffer[pos1].interpolate_rgb(&self.palette_buffer[pos2], frac);
+
+                    if !average.is_empty() {
+                        colour = DataExport::gamma_blend(colour, average[i] as f64);
+                    }
+
+                    let (r, g, b, _) = colour.to_linear_rgba_u8();
 
                     rgb_buffer[3 * i] = r; 
                     rgb_buffer[3 * i + 1] = g; 
@@ -142,9 +164,12 @@ impl RecolourExr {
                 }
             }
 
-            image::save_buffer(file_name.to_owned() + ".png", &rgb_buffer, dimensions.x() as u32, dimensions.y() as u32, image::ColorType::Rgb8).unwrap();
-        });
+            let png_file = file_name.to_owned() + ".png";
+
+            image::save_buffer(&png_file, &rgb_buffer, dimensions.x() as u32, dimensions.y() as u32, image::ColorType::Rgb8)
+                .map_err(|error| crate::Error::Write(png_file, Box::new(error)))
+        })?;
 
-        println!("Recolouring {} images took {} ms.", self.files.len(), colouring_time.elapsed().as_millis());
+        Ok(self.files.len())
     }
 }
@@ -0,0 +1,70 @@
+use parking_lot::{Condvar, Mutex};
+
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+// Controls a render from another thread. The workers check the handle between pixels and reference iterations,
+// so cancelling or pausing takes effect quickly without losing the work that has already been done
+#[derive(Clone, Default)]
+pub struct RenderHandle {
+    state: Arc<HandleState>,
+}
+
+#[derive(Default)]
+struct HandleState {
+    cancelled: AtomicBool,
+    paused: AtomicBool,
+    pause_lock: Mutex<()>,
+    resumed: Condvar,
+}
+
+impl RenderHandle {
+    pub fn new() -> Self {
+        RenderHandle::default()
+    }
+
+    // Stops the current frame. The finished pixels and approximations are kept so that the frame can be resumed
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+
+        // Paused workers need to wake up to see the cancellation
+        self.resume();
+    }
+
+    // Blocks the workers at the next check until resume or cancel is called
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        let _guard = self.state.pause_lock.lock();
+
+        self.state.paused.store(false, Ordering::SeqCst);
+        self.state.resumed.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::SeqCst)
+    }
+
+    // Waits while the render is paused, and then returns if the render has been cancelled
+    pub fn should_stop(&self) -> bool {
+        if self.is_paused() {
+            let mut guard = self.state.pause_lock.lock();
+
+            while self.is_paused() {
+                self.state.resumed.wait(&mut guard);
+            }
+        }
+
+        self.is_cancelled()
+    }
+
+    // The cancellation is cleared once the renderer has stopped, so the same handle can be used to resume
+    pub(crate) fn clear_cancelled(&self) {
+        self.state.cancelled.store(false, Ordering::SeqCst);
+    }
+}
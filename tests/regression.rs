@@ -0,0 +1,666 @@
+// Renders small versions of the shipped locations and compares them against the golden data in tests/golden
+// After an intentional change to the output, regenerate the golden data with UPDATE_GOLDEN=1 cargo test --test regression
+use config::{Config, File, FileFormat, Source};
+use exr::prelude::{FlatSamples, Levels, read_all_data_from_file};
+use rust_fractal::math::{get_misiurewicz, get_nucleus};
+use rust_fractal::renderer::FractalRenderer;
+use rust_fractal::settings::RenderSettings;
+use rust_fractal::util::data_export::FrameData;
+use rust_fractal::util::{ComplexArbitrary, ComplexExtended, MinibrotLocation, ProgressObserver, RenderHandle, RenderStage};
+
+use parking_lot::Mutex;
+
+use std::convert::TryInto;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+
+const IMAGE_WIDTH: usize = 64;
+const IMAGE_HEIGHT: usize = 48;
+
+// Allowed difference in the smooth iteration count of a pixel
+const SMOOTH_TOLERANCE: f64 = 1e-3;
+
+// Allowed relative difference in the colouring value of a pixel, which is absolute for values below one
+const COLOURING_TOLERANCE: f32 = 1e-3;
+
+// Allowed difference in each colour channel of a pixel, for the renders with lighting
+const RGB_TOLERANCE: u8 = 2;
+
+// Chaotic pixels can change with floating point differences between platforms
+const MAXIMUM_DIVERGENT_FRACTION: f64 = 0.005;
+
+// Allowed difference in the triangle inequality and curvature averages with and without the series approximation
+const AVERAGE_TOLERANCE: f32 = 0.01;
+
+// Only the first divergent pixels are listed in the failure message
+const MAXIMUM_REPORTED_PIXELS: usize = 20;
+
+struct RenderData {
+    iterations: Vec<u32>,
+    smooth: Vec<f32>,
+    // The distance estimate, the triangle inequality and curvature average, the atom domain period or the interior value,
+    // only stored for the renders with these colourings
+    colouring: Vec<f32>,
+    // Only stored for the renders with lighting, where the output colour depends on the blend
+    rgb: Vec<u8>,
+}
+
+impl RenderData {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let values = bytes.chunks_exact(4).map(|value| [value[0], value[1], value[2], value[3]]).collect::<Vec<[u8; 4]>>();
+
+        let width = u32::from_le_bytes(values[0]) as usize;
+        let height = u32::from_le_bytes(values[1]) as usize;
+
+        assert_eq!((width, height), (IMAGE_WIDTH, IMAGE_HEIGHT), "golden data has a different image size");
+
+        let number_pixels = width * height;
+
+        // The colour values are three bytes per pixel, so that they are the remainder after the four byte values
+        let rgb_start = bytes.len() - (bytes.len() - 8) % (4 * number_pixels);
+
+        RenderData {
+            iterations: values[2..(2 + number_pixels)].iter().map(|value| u32::from_le_bytes(*value)).collect(),
+            smooth: values[(2 + number_pixels)..(2 + 2 * number_pixels)].iter().map(|value| f32::from_le_bytes(*value)).collect(),
+            colouring: values[(2 + 2 * number_pixels)..(rgb_start / 4)].iter().map(|value| f32::from_le_bytes(*value)).collect(),
+            rgb: bytes[rgb_start..].to_vec(),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(8 + 4 * (self.iterations.len() + self.smooth.len() + self.colouring.len()) + self.rgb.len());
+
+        output.extend_from_slice(&(IMAGE_WIDTH as u32).to_le_bytes());
+        output.extend_from_slice(&(IMAGE_HEIGHT as u32).to_le_bytes());
+
+        for iteration in &self.iterations {
+            output.extend_from_slice(&iteration.to_le_bytes());
+        }
+
+        for smooth in &self.smooth {
+            output.extend_from_slice(&smooth.to_le_bytes());
+        }
+
+        for value in &self.colouring {
+            output.extend_from_slice(&value.to_le_bytes());
+        }
+
+        output.extend_from_slice(&self.rgb);
+
+        output
+    }
+}
+
+// Cancels the render once when the given stage starts
+struct CancelAtStage {
+    stage: Mutex<Option<RenderStage>>,
+    handle: RenderHandle,
+}
+
+impl ProgressObserver for CancelAtStage {
+    fn stage_started(&self, stage: RenderStage) {
+        let mut cancel_stage = self.stage.lock();
+
+        if *cancel_stage == Some(stage) {
+            *cancel_stage = None;
+            self.handle.cancel();
+        }
+    }
+}
+
+// Keeps the warnings that would otherwise be printed
+#[derive(Default)]
+struct Warnings {
+    messages: Mutex<Vec<String>>,
+}
+
+impl ProgressObserver for Warnings {
+    fn warning(&self, message: &str) {
+        self.messages.lock().push(message.to_owned());
+    }
+}
+
+fn location_settings<S: Source + Send + Sync + 'static>(location: S, overrides: &[(&str, &str)]) -> RenderSettings {
+    let mut builder = Config::builder()
+        .add_source(location)
+        .set_override("image_width", IMAGE_WIDTH as i64).unwrap()
+        .set_override("image_height", IMAGE_HEIGHT as i64).unwrap()
+        .set_override("export", "none").unwrap()
+        .set_override("show_output", false).unwrap()
+        .set_override("jitter", false).unwrap();
+
+    // Overrides are given as strings, so that boolean and integer settings need to be converted
+    for (key, value) in overrides {
+        builder = if let Ok(value) = value.parse::<bool>() {
+            builder.set_override(*key, value).unwrap()
+        } else if let Ok(value) = value.parse::<i64>() {
+            builder.set_override(*key, value).unwrap()
+        } else {
+            builder.set_override(*key, *value).unwrap()
+        };
+    }
+
+    RenderSettings::from_config(builder.build().unwrap()).unwrap()
+}
+
+// While the golden data is being generated, it is only written by the test that it belongs to
+fn updating_golden() -> bool {
+    std::env::var_os("UPDATE_GOLDEN").is_some()
+}
+
+fn location_renderer(location: &str, overrides: &[(&str, &str)]) -> FractalRenderer {
+    let location_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("locations").join(location.to_owned() + ".toml");
+
+    FractalRenderer::new(location_settings(File::from(location_path).required(true), overrides))
+}
+
+// Finds the lowest period minibrot in the location, with a renderer for the location written at its nucleus
+fn minibrot_renderer(location: &str, overrides: &[(&str, &str)]) -> (MinibrotLocation, FractalRenderer) {
+    let minibrot = location_renderer(location, &[]).find_minibrot(&RenderHandle::new()).unwrap().expect("no minibrot found in the image");
+    let renderer = FractalRenderer::new(location_settings(File::from_str(&minibrot.generate(), FileFormat::Toml), overrides));
+
+    (minibrot, renderer)
+}
+
+fn render_location(location: &str, overrides: &[(&str, &str)], cancel_stage: Option<RenderStage>) -> RenderData {
+    let mut renderer = location_renderer(location, overrides);
+
+    let handle = RenderHandle::new();
+
+    let frame = match cancel_stage {
+        Some(stage) => {
+            renderer.observer = Some(Arc::new(CancelAtStage {
+                stage: Mutex::new(Some(stage)),
+                handle: handle.clone(),
+            }));
+
+            assert!(renderer.render_frame_to_buffer(0, &handle).is_none(), "render was not cancelled");
+            assert_eq!(renderer.cancelled_stage(), Some(stage));
+
+            renderer.resume_frame_to_buffer(&handle).unwrap()
+        },
+        None => renderer.render_frame_to_buffer(0, &handle).unwrap()
+    };
+
+    render_data(frame, overrides)
+}
+
+// Keeps the data of the frame that is compared, with the colouring values selected from the overrides
+fn render_data(frame: FrameData, overrides: &[(&str, &str)]) -> RenderData {
+    // The smooth value is not written for interior pixels
+    let smooth = (0..frame.iterations.len()).map(|k| {
+        if frame.is_interior(k) {
+            0.0
+        } else {
+            frame.smooth[k]
+        }
+    }).collect();
+
+    let coloring_type = overrides.iter().find(|&&(key, _)| key == "coloring_type").map(|&(_, value)| value);
+    let interior_coloring = overrides.iter().any(|&(key, value)| key == "interior_coloring" && value != "black");
+
+    let colouring = match coloring_type {
+        Some(value) if value.starts_with("distance") => frame.distance,
+        Some("triangle_inequality") | Some("curvature") => frame.average,
+        Some(value) if value.starts_with("atom_domain") => frame.atom_domain.iter().map(|&period| period as f32).collect(),
+        _ if interior_coloring => frame.interior,
+        _ => Vec::new()
+    };
+
+    let rgb = if overrides.contains(&("lighting", "true")) {
+        frame.rgb
+    } else {
+        Vec::new()
+    };
+
+    RenderData {
+        iterations: frame.iterations,
+        smooth,
+        colouring,
+        rgb,
+    }
+}
+
+fn check_location(name: &str, location: &str, overrides: &[(&str, &str)]) {
+    check_golden(name, render_location(location, overrides, None));
+}
+
+// A frame cancelled at the start of a stage should match the golden data once it is resumed
+fn check_resumed(name: &str, location: &str, cancel_stage: RenderStage) {
+    check_render(name, render_location(location, &[], Some(cancel_stage)));
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(name.to_owned() + ".bin")
+}
+
+// Writes the golden data while it is being generated, and otherwise compares against it
+fn check_golden(name: &str, rendered: RenderData) {
+    if updating_golden() {
+        let golden_path = golden_path(name);
+
+        fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        fs::write(&golden_path, rendered.to_bytes()).unwrap();
+    } else {
+        check_render(name, rendered);
+    }
+}
+
+// Compares against golden data that belongs to another test, which is skipped while the golden data is being generated
+fn check_render(name: &str, rendered: RenderData) {
+    if updating_golden() {
+        return;
+    }
+
+    let golden_path = golden_path(name);
+
+    let golden = RenderData::from_bytes(&fs::read(&golden_path)
+        .unwrap_or_else(|_| panic!("missing golden data {}, generate it with UPDATE_GOLDEN=1", golden_path.display())));
+
+    assert_eq!(golden.colouring.len(), rendered.colouring.len(), "{}: the golden data and render differ in having colouring values", name);
+    assert_eq!(golden.rgb.len(), rendered.rgb.len(), "{}: the golden data and render differ in having colour values", name);
+
+    let divergent_pixels = (0..(IMAGE_WIDTH * IMAGE_HEIGHT)).filter(|&k| {
+        let expected = golden.iterations[k] as f64 + golden.smooth[k] as f64;
+        let found = rendered.iterations[k] as f64 + rendered.smooth[k] as f64;
+
+        let colouring_divergent = !golden.colouring.is_empty() &&
+            (golden.colouring[k] - rendered.colouring[k]).abs() > COLOURING_TOLERANCE * golden.colouring[k].max(1.0);
+
+        let rgb_divergent = !golden.rgb.is_empty() &&
+            (0..3).any(|channel| golden.rgb[3 * k + channel].abs_diff(rendered.rgb[3 * k + channel]) > RGB_TOLERANCE);
+
+        (expected - found).abs() > SMOOTH_TOLERANCE || colouring_divergent || rgb_divergent
+    }).collect::<Vec<usize>>();
+
+    let maximum_divergent_pixels = (MAXIMUM_DIVERGENT_FRACTION * (IMAGE_WIDTH * IMAGE_HEIGHT) as f64) as usize;
+
+    if divergent_pixels.len() > maximum_divergent_pixels {
+        let report = divergent_pixels.iter().take(MAXIMUM_REPORTED_PIXELS).map(|&k| {
+            let mut line = format!("  ({}, {}): expected {} + {:.6}, found {} + {:.6}",
+                k % IMAGE_WIDTH, k / IMAGE_WIDTH, golden.iterations[k], golden.smooth[k], rendered.iterations[k], rendered.smooth[k]);
+
+            if !golden.colouring.is_empty() {
+                line += &format!(", colouring expected {:.6}, found {:.6}", golden.colouring[k], rendered.colouring[k]);
+            }
+
+            if !golden.rgb.is_empty() {
+                line += &format!(", colour expected {:?}, found {:?}", &golden.rgb[(3 * k)..(3 * k + 3)], &rendered.rgb[(3 * k)..(3 * k + 3)]);
+            }
+
+            line
+        }).collect::<Vec<String>>().join("\n");
+
+        panic!("{}: {} of {} pixels diverge from the golden data (at most {} allowed)\n{}",
+            name, divergent_pixels.len(), IMAGE_WIDTH * IMAGE_HEIGHT, maximum_divergent_pixels, report);
+    }
+}
+
+macro_rules! regression_tests {
+    ($($name:ident: $location:expr, [$(($key:expr, $value:expr)),*];)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check_location(stringify!($name), $location, &[$(($key, $value)),*]);
+            }
+        )*
+    };
+}
+
+regression_tests! {
+    e14: "e14", [];
+    e14_bla: "e14", [("skip_method", "bla")];
+    e14_triangle_inequality: "e14", [("coloring_type", "triangle_inequality")];
+    e14_curvature: "e14", [("coloring_type", "curvature")];
+    e14_soft_light: "e14", [("coloring_type", "distance"), ("distance_transition", "10"), ("lighting", "true"), ("lighting_blend", "soft_light")];
+    e1000: "e1000", [];
+    e1000_bla: "e1000", [("skip_method", "bla")];
+    glitch_test_1: "glitch_test_1", [];
+    glitch_test_1_atom_domain: "glitch_test_1", [("coloring_type", "atom_domain")];
+    glitch_test_2: "glitch_test_2", [];
+    glitch_test_3: "glitch_test_3", [];
+    glitch_test_4: "glitch_test_4", [];
+    glitch_test_5: "glitch_test_5", [];
+    glitch_test_6: "glitch_test_6", [];
+    glitch_test_7: "glitch_test_7", [];
+    burning_ship_test: "burning_ship_test", [];
+    burning_ship_deep: "burning_ship_deep", [("coloring_type", "distance")];
+    tricorn_deep: "tricorn_deep", [];
+    celtic_deep: "celtic_deep", [];
+    buffalo_deep: "buffalo_deep", [];
+    perpendicular_burning_ship_deep: "perpendicular_burning_ship_deep", [];
+    cubic_test: "cubic_test", [];
+    julia_test: "julia_test", [];
+}
+
+#[test]
+fn resume_approximation() {
+    check_resumed("glitch_test_1", "glitch_test_1", RenderStage::Approximation);
+}
+
+#[test]
+fn resume_iteration() {
+    check_resumed("glitch_test_1", "glitch_test_1", RenderStage::Iteration);
+}
+
+#[test]
+fn resume_correction() {
+    check_resumed("glitch_test_1", "glitch_test_1", RenderStage::Correction);
+}
+
+// The series approximation of each variant should skip iterations without changing the output
+// The buffalo and perpendicular locations stop before the pixels close to the set, which are chaotic enough to change
+// with the error that the series approximation is allowed
+#[test]
+fn variant_series_approximation() {
+    check_render("burning_ship_deep", render_location("burning_ship_deep", &[("series_approximation_enabled", "false"), ("coloring_type", "distance")], None));
+
+    for location in ["tricorn_deep", "celtic_deep", "buffalo_deep", "perpendicular_burning_ship_deep"] {
+        check_render(location, render_location(location, &[("series_approximation_enabled", "false")], None));
+    }
+}
+
+// The first render saves the reference orbit, and the second should give the same output from the loaded orbit
+#[test]
+fn saved_reference() {
+    let reference_path = std::env::temp_dir().join(format!("rust_fractal_saved_reference_{}.ref", std::process::id()));
+    let reference_file = reference_path.to_string_lossy().into_owned();
+
+    for _ in 0..2 {
+        check_render("e1000", render_location("e1000", &[("reference_file", &reference_file)], None));
+        assert!(reference_path.exists(), "reference was not saved");
+    }
+
+    fs::remove_file(&reference_path).unwrap();
+}
+
+// Rendering the first frame again after increasing the maximum iteration should save the extended orbit
+#[test]
+fn extended_reference_saved() {
+    let reference_path = std::env::temp_dir().join(format!("rust_fractal_extended_reference_saved_{}.ref", std::process::id()));
+    let reference_file = reference_path.to_string_lossy().into_owned();
+
+    let mut renderer = location_renderer("e1000", &[("reference_file", &reference_file), ("iterations", "16000")]);
+
+    renderer.render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+    let saved_length = fs::metadata(&reference_path).unwrap().len();
+
+    renderer.maximum_iteration = 32000;
+    renderer.render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+
+    assert!(fs::metadata(&reference_path).unwrap().len() > saved_length, "extended reference was not saved");
+
+    check_render("e1000", render_location("e1000", &[("reference_file", &reference_file)], None));
+
+    fs::remove_file(&reference_path).unwrap();
+}
+
+// A reference file that can not be read or written is reported to the observer, and the render continues without it
+#[test]
+fn reference_file_warnings() {
+    let corrupt_path = std::env::temp_dir().join(format!("rust_fractal_corrupt_reference_{}.ref", std::process::id()));
+    let missing_path = std::env::temp_dir().join(format!("rust_fractal_missing_{}", std::process::id())).join("reference.ref");
+
+    fs::write(&corrupt_path, b"not a reference").unwrap();
+
+    for reference_path in [&corrupt_path, &missing_path] {
+        let reference_file = reference_path.to_string_lossy().into_owned();
+        let mut renderer = location_renderer("e14", &[("reference_file", &reference_file)]);
+
+        let warnings = Arc::new(Warnings::default());
+        renderer.observer = Some(warnings.clone());
+
+        assert!(renderer.render_frame_to_buffer(0, &RenderHandle::new()).is_some());
+        assert_eq!(warnings.messages.lock().len(), 1, "expected a warning for {}", reference_file);
+    }
+
+    fs::remove_file(&corrupt_path).unwrap();
+}
+
+// Compact references should give the same output as storing the full extended precision orbit
+#[test]
+fn compact_reference() {
+    for location in ["e1000", "glitch_test_1", "julia_test"] {
+        check_render(location, render_location(location, &[("compact_reference", "true")], None));
+    }
+
+    check_render("e1000_bla", render_location("e1000", &[("compact_reference", "true"), ("skip_method", "bla")], None));
+}
+
+// A saved orbit with a lower maximum iteration should be continued rather than calculated again
+#[test]
+fn extended_reference() {
+    let reference_path = std::env::temp_dir().join(format!("rust_fractal_extended_reference_{}.ref", std::process::id()));
+    let reference_file = reference_path.to_string_lossy().into_owned();
+
+    render_location("e1000", &[("reference_file", &reference_file), ("iterations", "16000")], None);
+
+    check_render("e1000", render_location("e1000", &[("reference_file", &reference_file)], None));
+
+    fs::remove_file(&reference_path).unwrap();
+}
+
+// Moving the reference to the nucleus of the minibrot in the image should give the same output
+// The series approximation is only accurate to a pixel, and e14 is chaotic enough that this error changes the output
+// with the reference, so it is compared with the bilinear approximation, which has a much smaller error
+#[test]
+fn nucleus_reference() {
+    check_render("e14_bla", render_location("e14", &[("reference_mode", "nucleus"), ("skip_method", "bla")], None));
+    check_render("e1000_bla", render_location("e1000", &[("reference_mode", "nucleus"), ("skip_method", "bla")], None));
+
+    for location in ["e1000", "glitch_test_1"] {
+        check_render(location, render_location(location, &[("reference_mode", "nucleus")], None));
+    }
+}
+
+// The minibrot found in the image should be written as a location centred on its nucleus
+#[test]
+fn minibrot_location() {
+    let (minibrot, mut renderer) = minibrot_renderer("e14", &[]);
+
+    assert_eq!(minibrot.period, 252);
+    assert!(minibrot.zoom.to_float() > 1e14);
+
+    let frame = renderer.render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+
+    // The nucleus is in the interior of the minibrot, so the centre of the image does not escape
+    assert!(frame.is_interior((IMAGE_HEIGHT / 2) * IMAGE_WIDTH + IMAGE_WIDTH / 2));
+}
+
+// The Misiurewicz point at the centre of the spiral should be found, with its orbit landing on a repelling fixed point
+#[test]
+fn misiurewicz_location() {
+    let location = location_renderer("spiral", &[]).find_misiurewicz(&RenderHandle::new()).unwrap().expect("no Misiurewicz point found in the image");
+
+    assert_eq!((location.misiurewicz.preperiod, location.misiurewicz.period), (29, 1));
+    assert!(location.misiurewicz.multiplier.norm().to_float() > 1.0);
+
+    // The written location is centred on the point
+    let settings = location_settings(File::from_str(&location.generate(), FileFormat::Toml), &[]);
+    assert!(settings.location.real.starts_with("2.62070620"));
+}
+
+// A cancelled search finds nothing, and leaves the handle ready for the next render
+#[test]
+fn cancelled_search() {
+    let mut renderer = location_renderer("e14", &[]);
+    let handle = RenderHandle::new();
+
+    handle.cancel();
+    assert!(renderer.find_minibrot(&handle).unwrap().is_none());
+
+    handle.cancel();
+    assert!(renderer.find_misiurewicz(&handle).unwrap().is_none());
+
+    assert!(renderer.render_frame_to_buffer(0, &handle).is_some(), "render stopped after a cancelled search");
+
+    // Newton's method is stopped by the handle, as it can take much longer than the reference at high periods
+    let cancelled = RenderHandle::new();
+    cancelled.cancel();
+
+    let guess = ComplexArbitrary::with_val(64, (-1.0, 0.0));
+    let current_estimate = Arc::new(Mutex::new(ComplexExtended::new2(0.0, 0.0, 0)));
+
+    assert!(get_nucleus(guess.clone(), 2, Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)), &cancelled, current_estimate).is_none());
+    assert!(get_misiurewicz(guess, 2, 1, &cancelled).is_none());
+}
+
+// Every iteration is checked for the atom domain, so the iteration data is unchanged and the minibrot has its own period
+#[test]
+fn atom_domain() {
+    // The atom domain is compared with its own golden data, so only the iteration data is compared here
+    let mut render = render_location("glitch_test_1", &[("coloring_type", "atom_domain")], None);
+    render.colouring.clear();
+
+    check_render("glitch_test_1", render);
+
+    let (_, mut renderer) = minibrot_renderer("e14", &[("coloring_type", "atom_domain_iteration")]);
+    let frame = renderer.render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+
+    assert_eq!(frame.atom_domain[(IMAGE_HEIGHT / 2) * IMAGE_WIDTH + IMAGE_WIDTH / 2], 252);
+
+    // The raw export writes the atom domain as the P channel
+    let output_path = std::env::temp_dir().join(format!("rust_fractal_atom_domain_{}", std::process::id()));
+    let output_directory = output_path.to_string_lossy().into_owned();
+
+    let overrides = [("coloring_type", "atom_domain"), ("export", "exr"), ("output_directory", &output_directory)];
+
+    let frame = location_renderer("glitch_test_1", &overrides).render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+    location_renderer("glitch_test_1", &overrides).render().unwrap();
+
+    let exr_file = fs::read_dir(&output_path).unwrap().next().unwrap().unwrap().path();
+    let raw_data = read_all_data_from_file(&exr_file).unwrap();
+
+    let atom_domain = raw_data.layer_data.iter()
+        .flat_map(|layer| layer.channel_data.list.iter())
+        .find(|channel| channel.name.eq("P"))
+        .map(|channel| match &channel.sample_data {
+            Levels::Singular(FlatSamples::U32(samples)) => samples.clone(),
+            _ => panic!("atom domain is not written as a single level of integers")
+        })
+        .expect("no atom domain channel in the raw export");
+
+    assert_eq!(atom_domain, frame.atom_domain);
+
+    fs::remove_dir_all(&output_path).unwrap();
+}
+
+#[test]
+fn interior_coloring() {
+    // Finding the attracting cycles ends interior pixels early, which should not change any of the iteration data
+    let mut render = render_location("e14", &[("interior_coloring", "distance")], None);
+    render.colouring.clear();
+
+    check_render("e14", render);
+
+    let (_, mut renderer) = minibrot_renderer("e14", &[("interior_coloring", "distance")]);
+    let frame = renderer.render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+
+    let centre = (IMAGE_HEIGHT / 2) * IMAGE_WIDTH + IMAGE_WIDTH / 2;
+
+    assert!(frame.is_interior(centre));
+    assert!(frame.interior[centre] > 0.0 && frame.interior[centre].is_finite(), "no interior distance at the nucleus: {}", frame.interior[centre]);
+
+    let (_, mut renderer) = minibrot_renderer("e14", &[("interior_coloring", "final_angle")]);
+    let frame = renderer.render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+
+    assert!(frame.interior.iter().all(|angle| angle.abs() <= std::f32::consts::PI));
+}
+
+// The interior distance estimate over the minibrot is compared against golden data
+#[test]
+fn minibrot_interior_distance() {
+    let overrides = [("interior_coloring", "distance")];
+
+    let (_, mut renderer) = minibrot_renderer("e14", &overrides);
+    let frame = renderer.render_frame_to_buffer(0, &RenderHandle::new()).unwrap();
+
+    check_golden("e14_minibrot_interior_distance", render_data(frame, &overrides));
+}
+
+#[test]
+fn average_colourings() {
+    for coloring_type in ["triangle_inequality", "curvature"] {
+        let frames = ["true", "false"].map(|series_approximation| {
+            location_renderer("e14", &[("coloring_type", coloring_type), ("series_approximation_enabled", series_approximation)])
+                .render_frame_to_buffer(0, &RenderHandle::new()).unwrap()
+        });
+
+        let exterior = (0..frames[0].iterations.len()).filter(|&k| !frames[0].is_interior(k)).collect::<Vec<usize>>();
+
+        assert!(exterior.iter().all(|&k| (0.0..=1.0).contains(&frames[0].average[k])), "{} average is outside of 0 to 1", coloring_type);
+        assert!(exterior.iter().any(|&k| frames[0].average[k] != frames[0].average[exterior[0]]), "{} average is constant", coloring_type);
+
+        // The iterations skipped by the series approximation start from the terms of the reference, which are close to the
+        // terms of each pixel
+        let maximum_difference = exterior.iter().map(|&k| (frames[0].average[k] - frames[1].average[k]).abs()).fold(0.0, f32::max);
+
+        assert!(maximum_difference < AVERAGE_TOLERANCE, "{} average differs by {} without the series approximation", coloring_type, maximum_difference);
+    }
+}
+
+#[test]
+fn distance_lighting() {
+    let render = |overrides: &[(&str, &str)]| {
+        let mut overrides = overrides.to_vec();
+        overrides.extend_from_slice(&[("coloring_type", "distance"), ("distance_transition", "10")]);
+
+        location_renderer("e14", &overrides).render_frame_to_buffer(0, &RenderHandle::new()).unwrap().rgb
+    };
+
+    let unlit = render(&[("lighting", "false")]);
+
+    // The palette colour is used rather than a greyscale distance
+    assert!(unlit.chunks_exact(3).any(|rgb| rgb[0] != rgb[1] || rgb[1] != rgb[2]), "distance colouring is greyscale");
+
+    let blends = ["overlay", "multiply", "soft_light"].map(|blend| render(&[("lighting", "true"), ("lighting_blend", blend)]));
+
+    for (index, blend) in blends.iter().enumerate() {
+        assert_ne!(*blend, unlit, "lighting blend {} is not shaded", index);
+    }
+
+    assert_ne!(blends[0], blends[1]);
+    assert_ne!(blends[0], blends[2]);
+}
+
+// Pixels in the removed centre of the later frames are not rendered, and should be written as interior rather than -1
+#[test]
+fn kfb_layout() {
+    let output_path = std::env::temp_dir().join(format!("rust_fractal_kfb_layout_{}", std::process::id()));
+    let output_directory = output_path.to_string_lossy().into_owned();
+
+    location_renderer("e14", &[("export", "kfb"), ("output_directory", &output_directory), ("remove_centre", "true"), ("frames", "2")]).render().unwrap();
+
+    let mut files = fs::read_dir(&output_path).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<PathBuf>>();
+    files.sort();
+
+    assert_eq!(files.len(), 2, "expected a file for each frame");
+
+    let data = fs::read(&files[1]).unwrap();
+    let read_i32 = |offset: usize| i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+
+    let number_pixels = IMAGE_WIDTH * IMAGE_HEIGHT;
+
+    assert_eq!(&data[0..3], b"KFB");
+    assert_eq!(read_i32(3), IMAGE_WIDTH as i32);
+    assert_eq!(read_i32(7), IMAGE_HEIGHT as i32);
+
+    let palette_offset = 11 + 4 * number_pixels;
+    assert!(read_i32(palette_offset) >= 1, "iteration division is less than 1");
+
+    let number_colors = read_i32(palette_offset + 4) as usize;
+    let maximum_iteration_offset = palette_offset + 8 + 3 * number_colors;
+    let maximum_iteration = read_i32(maximum_iteration_offset);
+
+    assert_eq!(maximum_iteration, 4036);
+    assert_eq!(data.len(), maximum_iteration_offset + 4 + 4 * number_pixels);
+
+    let iterations = (0..number_pixels).map(|k| read_i32(11 + 4 * k)).collect::<Vec<i32>>();
+
+    assert!(iterations.iter().all(|iteration| (0..=maximum_iteration).contains(iteration)), "iteration outside of 0 to the maximum iteration");
+    assert!(iterations.iter().any(|&iteration| iteration < maximum_iteration), "no exterior pixels were written");
+
+    fs::remove_dir_all(&output_path).unwrap();
+}
@@ -0,0 +1,77 @@
+// Checks that the settings are read and validated before anything is rendered
+use config::{Config, File, FileFormat};
+use rust_fractal::settings::{FractalFormula, RenderSettings};
+use rust_fractal::util::FractalType;
+use rust_fractal::Error;
+
+use std::fs;
+use std::path::PathBuf;
+
+#[test]
+fn fractal_power() {
+    for power in 2..=8 {
+        let settings = RenderSettings::builder().fractal_type(FractalFormula::Mandelbrot, power).build().unwrap();
+        assert_eq!(settings.fractal_type(), FractalType::Mandelbrot(power));
+    }
+
+    for power in [0, 1, 9] {
+        let result = RenderSettings::builder().fractal_type(FractalFormula::Mandelbrot, power).build();
+        assert!(matches!(result, Err(Error::InvalidSettings(_))), "power {} was accepted", power);
+    }
+
+    // The power is not silently ignored for the variants that only have the quadratic formula
+    let settings = RenderSettings::builder().fractal_type(FractalFormula::BurningShip, 2).build().unwrap();
+    assert_eq!(settings.fractal_type(), FractalType::BurningShip(2));
+
+    for fractal_type in [FractalFormula::BurningShip, FractalFormula::Tricorn, FractalFormula::PerpendicularBurningShip] {
+        let result = RenderSettings::builder().fractal_type(fractal_type, 3).build();
+        assert!(matches!(result, Err(Error::InvalidSettings(_))), "power 3 was accepted for {:?}", fractal_type);
+    }
+}
+
+fn toml_settings(text: &str) -> Result<RenderSettings, Error> {
+    RenderSettings::from_config(Config::builder().add_source(File::from_str(text, FileFormat::Toml)).build().unwrap())
+}
+
+#[test]
+fn palette_values() {
+    for text in ["palette = [255, 0, 0, 0]", "palette = [255, 0, 300]", "palette = []", "interior_palette = [255, 0, 0]", "interior_palette = [255, 0, 0, 0, 255, -1]"] {
+        let result = toml_settings(text);
+        assert!(matches!(result, Err(Error::InvalidPalette(_))), "{} gave {:?}", text, result.err());
+    }
+}
+
+#[test]
+fn invalid_zoom() {
+    for zoom in ["0", "-1E10", "1E ten", "deep"] {
+        let result = RenderSettings::builder().location("-0.75", "0", zoom).build();
+        assert!(matches!(result, Err(Error::InvalidZoom(_))), "zoom {} gave {:?}", zoom, result.err());
+    }
+}
+
+#[test]
+fn out_of_range_values() {
+    for text in ["iterations = 1", "image_width = 0", "zoom_scale = 0.0", "jitter_factor = -0.5", "jitter_factor = nan", "probe_sampling = 1",
+        "data_storage_interval = 0", "valid_iteration_probe_multiplier = 1.5", "glitch_tolerance = 0.0", "glitch_percentage = 101.0",
+        "palette_iteration_span = 0.0", "lighting_opacity = 2.0", "output_directory = \"\"", "reference_checkpoint_interval = 10",
+        "fractal_type = \"burningship\"\nreference_mode = \"nucleus\"", "export = \"tiff\""] {
+        let result = toml_settings(text);
+        assert!(matches!(result, Err(Error::InvalidSettings(_))), "{} gave {:?}", text.replace('\n', ", "), result.err());
+    }
+}
+
+#[test]
+fn unknown_keys() {
+    let config = Config::builder().add_source(File::from_str("iteratons = 1000\nzoom = \"1E5\"\npalete = [0, 0, 0]", FileFormat::Toml)).build().unwrap();
+    assert_eq!(RenderSettings::unknown_keys(&config), vec!["iteratons", "palete"]);
+
+    // Every key in the default options and the example locations is read by the renderer
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let mut paths = vec![root.join("default.toml")];
+    paths.extend(fs::read_dir(root.join("locations")).unwrap().map(|entry| entry.unwrap().path()));
+
+    for path in paths {
+        let config = Config::builder().add_source(File::from(path.clone())).build().unwrap();
+        assert_eq!(RenderSettings::unknown_keys(&config), Vec::<String>::new(), "unknown keys in {}", path.display());
+    }
+}
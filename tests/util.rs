@@ -0,0 +1,106 @@
+// Checks the number types and file formats that the renders are built on, without rendering anything
+use colorgrad::Color;
+use config::{Config, File};
+use rust_fractal::settings::RenderSettings;
+use rust_fractal::util::{ComplexExtended, FloatExtended, FractalType, KfrFormat, KfrLocation};
+
+use std::cmp::Ordering;
+
+#[test]
+fn float_extended_ordering_with_zero() {
+    let zero = FloatExtended::new(0.0, 0);
+    let small = FloatExtended::new(0.5, -2000);
+    let large = FloatExtended::new(0.5, 2000);
+    let negative = FloatExtended::new(-0.5, -2000);
+
+    // Zero has no meaningful exponent, so only the sign of the other mantissa decides the order
+    for value in [small, large] {
+        assert_eq!(value.partial_cmp(&zero), Some(Ordering::Greater));
+        assert_eq!(zero.partial_cmp(&value), Some(Ordering::Less));
+        assert!(value > zero);
+    }
+
+    assert_eq!(negative.partial_cmp(&zero), Some(Ordering::Less));
+    assert_eq!(zero.partial_cmp(&negative), Some(Ordering::Greater));
+    assert_eq!(zero.partial_cmp(&FloatExtended::new(0.0, 5)), Some(Ordering::Equal));
+
+    assert!(small < large);
+}
+
+#[test]
+fn complex_extended_scale_to_exponent() {
+    let mut value = ComplexExtended::new2(0.75, -0.25, -40);
+
+    // Changing the exponent keeps the value, moving the difference into the mantissa
+    for exponent in [-60, -40, -10] {
+        value.scale_to_exponent(exponent);
+
+        assert_eq!(value.exponent, exponent);
+        assert_eq!(value.mantissa.re * 2.0f64.powi(exponent + 40), 0.75);
+        assert_eq!(value.mantissa.im * 2.0f64.powi(exponent + 40), -0.25);
+    }
+}
+
+fn kfr_settings(text: &str) -> RenderSettings {
+    let config = Config::builder()
+        .add_source(File::from_str(text, KfrFormat))
+        .build()
+        .unwrap();
+
+    RenderSettings::from_config(config).unwrap()
+}
+
+#[test]
+fn kfr_parse() {
+    // Written by Kalles Fraktaler, with keys that have no setting and a decimal zoom
+    let settings = kfr_settings("Re: -1.25066\r\nIm: 0.02012\r\nZoom: 125000.5\r\nIterations: 4000\r\nIterDiv: 0.250000\r\n\
+        ColorOffset: 256\r\nRotate: 30.000000\r\nColors: 255,0,0,0,255,0,0,0,255,\r\nSmooth: 1\r\nPower: 3\r\nFractalType: 0\r\n");
+
+    assert_eq!(settings.location.real, "-1.25066");
+    assert_eq!(settings.location.imag, "0.02012");
+    assert_eq!(settings.location.zoom, "1.250005E5");
+    assert_eq!(settings.location.iterations, 4000);
+    assert_eq!(settings.location.rotate, 30.0);
+    assert_eq!(settings.fractal_type(), FractalType::Mandelbrot(3));
+    assert_eq!(settings.colouring.palette, Some(vec![255, 0, 0, 0, 255, 0, 0, 0, 255]));
+    assert_eq!(settings.colouring.palette_offset, 0.25);
+    assert_eq!(settings.colouring.palette_iteration_span, 256.0);
+
+    // Formulas that are not implemented, and other powers of the abs variations, would render a different fractal
+    for text in ["Iterations: many\r\n", "FractalType: 10\r\n", "Power: 3\r\nFractalType: 1\r\n", "FractalType: 4\r\nPower: 3\r\n"] {
+        assert!(Config::builder().add_source(File::from_str(text, KfrFormat)).build().is_err(), "{:?} was read", text);
+    }
+}
+
+#[test]
+fn kfr_round_trip() {
+    let palette = [Color::from_rgba8(255, 0, 0, 255), Color::from_rgba8(0, 128, 0, 255), Color::from_rgba8(10, 20, 30, 255), Color::from_rgba8(255, 0, 0, 255)];
+
+    for fractal_type in [FractalType::Mandelbrot(4), FractalType::BurningShip(2), FractalType::Tricorn(2), FractalType::Celtic(2), FractalType::Buffalo(2), FractalType::PerpendicularBurningShip(2)] {
+        let text = KfrLocation {
+            real: String::from("-1.7400623825793399052208441670658256382966417204361718668798624184611829"),
+            imag: String::from("0.0281753397792110489924115211443195096875390767429906085704013095958801"),
+            zoom: String::from("1.00000000000E60"),
+            iterations: 12000,
+            rotate: 45.0,
+            fractal_type,
+            palette: &palette,
+            palette_offset: 0.5,
+            palette_iteration_span: 512.0,
+        }.generate();
+
+        let settings = kfr_settings(&text);
+
+        assert_eq!(settings.location.real, "-1.7400623825793399052208441670658256382966417204361718668798624184611829");
+        assert_eq!(settings.location.imag, "0.0281753397792110489924115211443195096875390767429906085704013095958801");
+        assert_eq!(settings.location.zoom, "1.00000000000E60");
+        assert_eq!(settings.location.iterations, 12000);
+        assert_eq!(settings.location.rotate, 45.0);
+        assert_eq!(settings.fractal_type(), fractal_type);
+
+        // The closing colour of the cyclic palette is not written
+        assert_eq!(settings.colouring.palette, Some(vec![255, 0, 0, 0, 128, 0, 10, 20, 30]));
+        assert_eq!(settings.colouring.palette_offset, 0.5);
+        assert_eq!(settings.colouring.palette_iteration_span, 512.0);
+    }
+}